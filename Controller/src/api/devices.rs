@@ -0,0 +1,42 @@
+/* Copyright 2022-present University of Tuebingen, Chair of Communication Networks
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
+ */
+
+use std::sync::Arc;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::Serialize;
+use crate::api::online::Asic;
+
+#[derive(Serialize, JsonSchema, Clone)]
+pub struct Device {
+    /// Index of the device. The REST API of the device is served at `/api/devices/{index}`.
+    pub(crate) index: usize,
+    pub(crate) name: String,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) device_id: u32,
+    pub(crate) asic: Asic
+}
+
+/// Devices endpoint
+pub async fn devices(State(devices): State<Arc<Vec<Device>>>) -> (StatusCode, Json<Vec<Device>>) {
+    (StatusCode::OK, Json(devices.to_vec()))
+}
@@ -1098,6 +1098,16 @@ lazy_static!(
             "7": 0.0
         }
     },
+    "flow_rx": {
+        "128": {
+            "dns": {
+                "packets": 1024,
+                "bytes": 92160,
+                "packet_rate": 10.0,
+                "rate_l2": 7200.0
+            }
+        }
+    },
     "frame_type_data": {
         "152": {
             "tx": {
@@ -1509,7 +1519,47 @@ lazy_static!(
             "max": 1060,
             "current": 1040,
             "jitter": 5.407541284205046,
-            "n": 1162
+            "n": 1162,
+            "rfc3550_jitter": 6.25,
+            "ipdv_mean": 7.2,
+            "ipdv_min": -40,
+            "ipdv_max": 40,
+            "pdv": 40,
+            "percentiles": {
+                "50": 1040,
+                "90": 1050,
+                "99": 1060,
+                "99.9": 1060
+            },
+            "histogram": [
+                {
+                    "lower": 1000,
+                    "upper": 1188,
+                    "count": 1162
+                }
+            ]
+        }
+    },
+    "app_rtts": {
+        "136": {
+            "1": {
+                "mean": 1040.6738382099827,
+                "min": 1020,
+                "max": 1060,
+                "current": 1040,
+                "jitter": 5.407541284205046,
+                "n": 1162,
+                "rfc3550_jitter": 6.25,
+                "ipdv_mean": 7.2,
+                "ipdv_min": -40,
+                "ipdv_max": 40,
+                "pdv": 40,
+                "percentiles": {
+                    "50": 1040,
+                    "99.9": 1060
+                },
+                "histogram": []
+            }
         }
     },
     "packet_loss": {
@@ -1536,6 +1586,79 @@ lazy_static!(
         "176": 0,
         "60": 0
     },
+    "app_packet_loss": {
+        "136": {
+            "1": 0,
+            "2": 0
+        }
+    },
+    "app_out_of_order": {
+        "136": {
+            "1": 0,
+            "2": 0
+        }
+    },
+    "reordering": {
+        "136": {
+            "duplicates": 0,
+            "max_extent": 0,
+            "reorder_density": [
+                {"lower": 0, "upper": 0, "count": 0, "density": 0.0},
+                {"lower": 1, "upper": 1, "count": 0, "density": 0.0},
+                {"lower": 2, "upper": 2, "count": 0, "density": 0.0},
+                {"lower": 3, "upper": 4, "count": 0, "density": 0.0},
+                {"lower": 5, "upper": 8, "count": 0, "density": 0.0},
+                {"lower": 9, "upper": 16, "count": 0, "density": 0.0},
+                {"lower": 17, "upper": 32, "count": 0, "density": 0.0},
+                {"lower": 33, "upper": u32::MAX, "count": 0, "density": 0.0}
+            ]
+        }
+    },
+    "app_reordering": {
+        "136": {
+            "1": {
+                "duplicates": 0,
+                "max_extent": 0,
+                "reorder_density": [
+                    {"lower": 0, "upper": 0, "count": 0, "density": 0.0},
+                    {"lower": 1, "upper": 1, "count": 0, "density": 0.0},
+                    {"lower": 2, "upper": 2, "count": 0, "density": 0.0},
+                    {"lower": 3, "upper": 4, "count": 0, "density": 0.0},
+                    {"lower": 5, "upper": 8, "count": 0, "density": 0.0},
+                    {"lower": 9, "upper": 16, "count": 0, "density": 0.0},
+                    {"lower": 17, "upper": 32, "count": 0, "density": 0.0},
+                    {"lower": 33, "upper": u32::MAX, "count": 0, "density": 0.0}
+                ]
+            },
+            "2": {
+                "duplicates": 0,
+                "max_extent": 0,
+                "reorder_density": [
+                    {"lower": 0, "upper": 0, "count": 0, "density": 0.0},
+                    {"lower": 1, "upper": 1, "count": 0, "density": 0.0},
+                    {"lower": 2, "upper": 2, "count": 0, "density": 0.0},
+                    {"lower": 3, "upper": 4, "count": 0, "density": 0.0},
+                    {"lower": 5, "upper": 8, "count": 0, "density": 0.0},
+                    {"lower": 9, "upper": 16, "count": 0, "density": 0.0},
+                    {"lower": 17, "upper": 32, "count": 0, "density": 0.0},
+                    {"lower": 33, "upper": u32::MAX, "count": 0, "density": 0.0}
+                ]
+            }
+        }
+    },
+    "rx_groups": {
+        "128": {
+            "rx_ports": [136, 144],
+            "packet_loss": 0,
+            "out_of_order": 0,
+            "rx_rate_l1": 0.0,
+            "rx_rate_l2": 0.0,
+            "share": {
+                "136": 0.0,
+                "144": 0.0
+            }
+        }
+    },
     "elapsed_time": 0
 }).to_string();
 );
\ No newline at end of file
@@ -22,6 +22,8 @@ lazy_static! {
                     ip_dst_mask: Ipv4Addr::from_str("0.0.0.0").unwrap()
                 },
                 active: true,
+                traffic_rate: None,
+                app_id: None,
                 mpls_stack: None,
                 vxlan: None,
                 vlan: Some(Vlan {
@@ -41,18 +43,24 @@ lazy_static! {
                 encapsulation: Encapsulation::Vlan,
                 traffic_rate: 80f32,
                 burst: 100,
+                required_accuracy: None,
                 vxlan: false,
                 n_packets: Some(5),
                 timeout: Some(88),
                 generation_accuracy: Some(100f32),
+                iat_jitter: None,
                 n_pipes: Some(2),
+                generation_ports: Some(vec![68, 196]),
                 number_of_lse: None
             }
         ],
         port_tx_rx_mapping: HashMap::from([(128, 136)]),
+        port_tx_rx_groups: None,
         duration: None,
         name: None,
         all_test: None,
+        analyze_filters: None,
+        flow_rules: None
     };
 
     pub static ref EXAMPLE_GET_2: TrafficGenData = TrafficGenData {
@@ -72,6 +80,8 @@ lazy_static! {
                     ip_dst_mask: Ipv4Addr::from_str("0.0.0.0").unwrap()
                 },
                 active: true,
+                traffic_rate: None,
+                app_id: None,
                 vxlan: Some(VxLAN {
                     eth_src: "32:D5:42:2A:F6:92".to_string(),
                     eth_dst: "81:E7:9D:E3:AD:47".to_string(),
@@ -92,18 +102,24 @@ lazy_static! {
                 encapsulation: Encapsulation::None,
                 traffic_rate: 100f32,
                 burst: 100,
+                required_accuracy: None,
                 vxlan: true,
                 n_packets: Some(5),
                 timeout: Some(876),
                 generation_accuracy: Some(99.908676f32),
+                iat_jitter: Some(0.32f32),
                 n_pipes: Some(2),
+                generation_ports: Some(vec![68, 196]),
                 number_of_lse: None
             }
         ],
         port_tx_rx_mapping: HashMap::from([(128, 136)]),
+        port_tx_rx_groups: None,
         duration: None,
         name: None,
         all_test: None,
+        analyze_filters: None,
+        flow_rules: None
     };
 
 
@@ -115,10 +131,13 @@ lazy_static! {
         number_of_lse: None,
         traffic_rate: 100f32,
         burst: 100,
+        required_accuracy: None,
         n_packets: Some(5),
         timeout: Some(876),
         generation_accuracy: Some(99.908676f32),
+        iat_jitter: Some(0.32f32),
         n_pipes: Some(2),
+        generation_ports: Some(vec![68, 196]),
         vxlan: true
     }];
 
@@ -139,6 +158,8 @@ lazy_static! {
                     ip_dst_mask: Ipv4Addr::from_str("0.0.0.0").unwrap()
                 },
                 active: true,
+                traffic_rate: None,
+                app_id: None,
                 vxlan: Some(VxLAN {
                     eth_src: "32:D5:42:2A:F6:92".to_string(),
                     eth_dst: "81:E7:9D:E3:AD:47".to_string(),
@@ -159,18 +180,24 @@ lazy_static! {
                 encapsulation: Encapsulation::None,
                 traffic_rate: 100f32,
                 burst: 100,
+                required_accuracy: None,
                 vxlan: true,
                 generation_accuracy: None,
+                iat_jitter: None,
                 n_packets: None,
                 n_pipes: None,
+                generation_ports: None,
                 timeout: None,
                 number_of_lse: None
             }
         ],
         port_tx_rx_mapping: HashMap::from([(128, 136)]),
+        port_tx_rx_groups: None,
         duration: None,
         all_test: None,
         name: None,
+        analyze_filters: None,
+        flow_rules: None,
 };
 
     pub static ref EXAMPLE_POST_2_REQUEST: TrafficGenData = TrafficGenData {
@@ -190,6 +217,8 @@ lazy_static! {
                     ip_dst_mask: Ipv4Addr::from_str("0.0.0.0").unwrap()
                 },
                 active: true,
+                traffic_rate: None,
+                app_id: None,
                 mpls_stack: None,
                 vxlan: None,
                 vlan: Some(Vlan {
@@ -209,18 +238,24 @@ lazy_static! {
                 encapsulation: Encapsulation::Vlan,
                 traffic_rate: 80f32,
                 burst: 100,
+                required_accuracy: None,
                 vxlan: false,
                 generation_accuracy: None,
+                iat_jitter: None,
                 n_packets: None,
                 n_pipes: None,
+                generation_ports: None,
                 timeout: None,
                 number_of_lse: None
             }
         ],
         port_tx_rx_mapping: HashMap::from([(128, 136)]),
+        port_tx_rx_groups: None,
         duration: None,
         all_test: None,
         name: None,
+        analyze_filters: None,
+        flow_rules: None,
     };
 
     pub static ref EXAMPLE_POST_2_RESPONSE: Vec<Stream> = vec![Stream {
@@ -231,19 +266,25 @@ lazy_static! {
         number_of_lse: None,
         traffic_rate: 80f32,
         burst: 100,
+        required_accuracy: None,
         n_packets: Some(5),
         timeout: Some(88),
         generation_accuracy: Some(100f32),
+        iat_jitter: Some(7.04f32),
         n_pipes: Some(2),
+        generation_ports: Some(vec![68, 196]),
         vxlan: false
     }];
 
     pub static ref EXAMPLE_POST_3_REQUEST: TrafficGenData = TrafficGenData {
         mode: GenerationMode::Poisson,
         port_tx_rx_mapping: HashMap::from([(68, 68)]),
+        port_tx_rx_groups: None,
         stream_settings: vec![
             StreamSetting {
                 active: true,
+                traffic_rate: None,
+                app_id: None,
                 ethernet: Ethernet {
                     eth_src: "32:D5:42:2A:F6:92".to_string(),
                     eth_dst: "81:E7:9D:E3:AD:47".to_string(),
@@ -269,18 +310,23 @@ lazy_static! {
                 frame_size: 64,
                 traffic_rate: 30f32,
                 burst: 100,
+                required_accuracy: None,
                 encapsulation: Encapsulation::None,
                 vxlan: false,
                 number_of_lse: None,
                 timeout: None,
                 n_packets: None,
                 generation_accuracy: None,
+                iat_jitter: None,
                 n_pipes: None,
+                generation_ports: None,
             }
         ],
         duration: None,
         all_test: None,
         name: None,
+        analyze_filters: None,
+        flow_rules: None,
     };
 }
 
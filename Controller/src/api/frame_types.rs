@@ -0,0 +1,95 @@
+/* Copyright 2022-present University of Tuebingen, Chair of Communication Networks
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
+ */
+
+use std::sync::Arc;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde::Deserialize;
+
+use crate::api::server::Error;
+use crate::AppState;
+use crate::core::FrameTypeClassifier;
+
+#[derive(Debug, Deserialize)]
+pub struct FrameTypeParams {
+    name: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/frame_types",
+    responses(
+    (status = 200,
+    description = "Returns the user-defined frame types.",
+    body = Vec<FrameTypeClassifier>))
+)]
+/// Returns the user-defined frame types
+pub async fn frame_types(State(state): State<Arc<AppState>>) -> Response {
+    (StatusCode::OK, Json(state.frame_type_monitor.lock().await.custom_types())).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/frame_types",
+    request_body = FrameTypeClassifier,
+    responses(
+    (status = 201,
+    description = "Adds the user-defined frame type and returns all user-defined frame types.",
+    body = Vec<FrameTypeClassifier>),
+    (status = 400,
+    description = "Returned when the frame type is not valid."))
+)]
+/// Adds a user-defined frame type that is counted in the frame type statistics under its name
+pub async fn add_frame_type(State(state): State<Arc<AppState>>, Json(payload): Json<FrameTypeClassifier>) -> Response {
+    let frame_type_monitor = &mut state.frame_type_monitor.lock().await;
+
+    if let Err(err) = frame_type_monitor.validate_custom_type(&payload) {
+        return (StatusCode::BAD_REQUEST, Json(Error::new(err))).into_response();
+    }
+
+    match frame_type_monitor.add_custom_type(&*state.switch.read().await, payload).await {
+        Ok(_) => (StatusCode::CREATED, Json(frame_type_monitor.custom_types())).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/frame_types",
+    params(
+        ("name" = String, Query, description = "Name of the user-defined frame type")
+    ),
+    responses(
+    (status = 200,
+    description = "Removes the user-defined frame type and returns the remaining user-defined frame types.",
+    body = Vec<FrameTypeClassifier>),
+    (status = 404,
+    description = "Returned when the frame type does not exist."))
+)]
+/// Removes a user-defined frame type
+pub async fn remove_frame_type(State(state): State<Arc<AppState>>, Query(params): Query<FrameTypeParams>) -> Response {
+    let frame_type_monitor = &mut state.frame_type_monitor.lock().await;
+
+    match frame_type_monitor.remove_custom_type(&*state.switch.read().await, &params.name).await {
+        Ok(true) => (StatusCode::OK, Json(frame_type_monitor.custom_types())).into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(Error::new(format!("Frame type {} does not exist.", params.name)))).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
+    }
+}
@@ -17,21 +17,35 @@
  * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
  */
 
-use std::cmp::max;
-use std::collections::VecDeque;
+use crate::core::statistics::{HistogramBin, HistogramConfig};
 
-pub fn average(numbers: &VecDeque<u64>) -> f64 {
-    numbers.iter().sum::<u64>() as f64 / max(1, numbers.len()) as f64
+/// Returns the `p`-th percentile of the sorted `numbers` using the nearest-rank method.
+pub fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+
+    let rank = (p / 100f64 * sorted.len() as f64).ceil() as usize;
+
+    sorted[rank.clamp(1, sorted.len()) - 1]
 }
 
-pub fn std(numbers: &VecDeque<u64>) -> f64 {
-    let mean = numbers.iter().sum::<u64>() as f64 / max(1, numbers.len()) as f64;
-    let sum_of_squared_diff: f64 = numbers.iter().map(|x| f64::powf(mean - (*x as f64), 2f64)).sum();
+/// Counts the `numbers` in the buckets of the histogram described by `config`.
+/// Values outside the histogram range are counted in the first / last bucket.
+pub fn histogram(numbers: &[u64], config: &HistogramConfig) -> Vec<HistogramBin> {
+    let edges = config.edges();
+
+    let mut bins: Vec<HistogramBin> = edges.windows(2).map(|e| HistogramBin { lower: e[0], upper: e[1], count: 0 }).collect();
 
-    if numbers.len() < 2 {
-        return 0f64;
+    if bins.is_empty() {
+        return bins;
     }
 
-    // sample standard deviation
-    (sum_of_squared_diff / max(1, numbers.len() - 1) as f64).sqrt()
-}
\ No newline at end of file
+    for n in numbers {
+        // index of the first bucket whose upper bound is larger than n
+        let index = bins.partition_point(|b| b.upper <= *n).min(bins.len() - 1);
+        bins[index].count += 1;
+    }
+
+    bins
+}
@@ -17,28 +17,84 @@
  * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
  */
 
+use std::collections::{BTreeMap, HashMap, HashSet};
+
 use crate::core::traffic_gen_core::types::*;
 use crate::api::server::Error;
+use crate::PortMapping;
+use crate::core::FLOW_MONITOR_SIZE;
 use crate::core::traffic_gen_core::const_definitions::{MAX_BUFFER_SIZE, MAX_NUM_MPLS_LABEL, TG_MAX_RATE, TG_MAX_RATE_TF2};
 use crate::core::traffic_gen_core::helper::calculate_overhead;
+use crate::core::traffic_gen_core::optimization::calculate_send_behaviour;
+use crate::core::traffic_gen_core::planner::resolve_rate_overrides;
+use crate::error::P4TGError;
 use crate::core::traffic_gen_core::types::{Encapsulation, GenerationMode};
 
 /// Validates an incoming traffic generation request.
-/// Checks if the MPLS configuration is correct, i.e., if the MPLS stack matches the number of LSEs.
-pub fn validate_request(streams: &[Stream], settings: &[StreamSetting], mode: &GenerationMode, is_tofino2: bool) -> Result<(), Error> {
+/// Returns the first validation error of [request_errors].
+pub fn validate_request(payload: &TrafficGenData, streams: &[Stream], settings: &[StreamSetting], tx_rx_mapping: &HashMap<u32, Vec<u32>>,
+                        port_mapping: &HashMap<u32, PortMapping>, is_tofino2: bool, port_speeds: &HashMap<u32, f32>) -> Result<(), Error> {
+    match request_errors(payload, streams, settings, tx_rx_mapping, port_mapping, is_tofino2, port_speeds).into_iter().next() {
+        Some(message) => Err(Error::new(message)),
+        None => Ok(())
+    }
+}
+
+/// Validates an incoming traffic generation request and collects all validation errors.
+/// The start of a traffic generation and its planning use this validation s.t. both accept the same requests.
+///
+/// # Arguments
+///
+/// * `payload`: Traffic generation request
+/// * `streams`: Streams of the active stream settings
+/// * `settings`: Active stream settings
+/// * `tx_rx_mapping`: Mapping of TX port to the set of RX ports, see [rx_port_groups](crate::core::traffic_gen_core::helper::rx_port_groups)
+/// * `port_mapping`: Mapping of the traffic generation ports to their recirculation ports
+/// * `is_tofino2`: Indicates whether the device is a Tofino 2
+/// * `port_speeds`: Speed in Gbps of the front panel ports
+pub fn request_errors(payload: &TrafficGenData, streams: &[Stream], settings: &[StreamSetting], tx_rx_mapping: &HashMap<u32, Vec<u32>>,
+                      port_mapping: &HashMap<u32, PortMapping>, is_tofino2: bool, port_speeds: &HashMap<u32, f32>) -> Vec<String> {
+    let mut errors = validation_errors(streams, settings, &payload.mode, is_tofino2, port_speeds);
+
+    let results = [validate_rx_port_groups(tx_rx_mapping, port_mapping, streams, settings),
+                   validate_analyze_filters(payload.analyze_filters.as_deref().unwrap_or_default(), &payload.mode, tx_rx_mapping),
+                   validate_flow_rules(payload.flow_rules.as_deref().unwrap_or_default(), &payload.mode, tx_rx_mapping)];
+
+    errors.extend(results.into_iter().filter_map(|result| result.err().map(|err| err.message)));
+
+    errors
+}
+
+/// Validates the streams and stream settings of a traffic generation request and collects all validation errors.
+/// `port_speeds` contains the speed in Gbps of the front panel ports as reported by the port manager.
+pub fn validation_errors(streams: &[Stream], settings: &[StreamSetting], mode: &GenerationMode, is_tofino2: bool, port_speeds: &HashMap<u32, f32>) -> Vec<String> {
+    let mut errors = vec![];
+
+    // Poisson traffic is only allowed to have a single stream
+    if *mode == GenerationMode::Poisson && streams.len() != 1 {
+        errors.push("Poisson generation mode only allows for one stream.".to_owned());
+    }
+
+    // no streams should be generated in monitor/analyze mode
+    if *mode == GenerationMode::Analyze && !streams.is_empty() {
+        errors.push("No stream definition in analyze mode allowed.".to_owned());
+    }
+
     for stream in streams.iter(){
         // Check max number of MPLS labels
         if stream.encapsulation == Encapsulation::Mpls {
-            if stream.number_of_lse.is_none() {
-                return Err(Error::new(format!("number_of_lse missing for stream #{}", stream.stream_id)))
-            }
-
-            if stream.number_of_lse.unwrap() > MAX_NUM_MPLS_LABEL {
-                return Err(Error::new(format!("Configured number of LSEs in stream with ID #{} exceeded maximum of {}.", stream.stream_id, MAX_NUM_MPLS_LABEL)));
+            match stream.number_of_lse {
+                None => errors.push(format!("number_of_lse missing for stream #{}", stream.stream_id)),
+                Some(n) if n > MAX_NUM_MPLS_LABEL => errors.push(format!("Configured number of LSEs in stream with ID #{} exceeded maximum of {}.", stream.stream_id, MAX_NUM_MPLS_LABEL)),
+                Some(0) => errors.push(format!("MPLS encapsulation selected for stream with ID #{} but #LSE is zero.", stream.stream_id)),
+                _ => {}
             }
+        }
 
-            if stream.number_of_lse.unwrap() == 0 {
-                return Err(Error::new(format!("MPLS encapsulation selected for stream with ID #{} but #LSE is zero.", stream.stream_id)));
+        // Check required accuracy
+        if let Some(required_accuracy) = stream.required_accuracy {
+            if !(required_accuracy > 0f32 && required_accuracy <= 100f32) {
+                errors.push(format!("Required accuracy of stream with ID #{} has to be in (0, 100].", stream.stream_id));
             }
         }
 
@@ -46,38 +102,91 @@ pub fn validate_request(streams: &[Stream], settings: &[StreamSetting], mode: &G
             if setting.stream_id == stream.stream_id {
                 // check VLAN settings
                 if (stream.encapsulation == Encapsulation::Vlan || stream.encapsulation == Encapsulation::QinQ) && setting.vlan.is_none() {
-                    return Err(Error::new(format!("VLAN encapsulation selected for stream with iD #{}, but no VLAN settings provided for port {}.", stream.stream_id, setting.port)))
+                    errors.push(format!("VLAN encapsulation selected for stream with iD #{}, but no VLAN settings provided for port {}.", stream.stream_id, setting.port));
                 }
 
                 // check MPLS
-                // check that mpls stack is set
-                if stream.encapsulation == Encapsulation::Mpls && setting.mpls_stack.is_none() {
-                    return Err(Error::new(format!("No MPLS stack provided for stream with ID #{} on port {}.", stream.stream_id, setting.port)))
+                if stream.encapsulation == Encapsulation::Mpls {
+                    match &setting.mpls_stack {
+                        // check that mpls stack is set
+                        None => errors.push(format!("No MPLS stack provided for stream with ID #{} on port {}.", stream.stream_id, setting.port)),
+                        // Validate if the configured number_of_lse per stream matches the MPLS stack size
+                        Some(stack) => if stream.number_of_lse.is_some_and(|n| stack.len() != n as usize) {
+                            errors.push(format!("Number of LSEs in stream with ID #{} does not match length of the MPLS stack.", setting.stream_id));
+                        }
+                    }
                 }
+            }
 
-                // Validate if the configured number_of_lse per stream matches the MPLS stack size
-                if stream.encapsulation == Encapsulation::Mpls && setting.mpls_stack.as_ref().unwrap().len() != stream.number_of_lse.unwrap() as usize {
-                    return Err(Error::new(format!("Number of LSEs in stream with ID #{} does not match length of the MPLS stack.", setting.stream_id)));
-                }
+            // Check rate override
+            if setting.stream_id == stream.stream_id && setting.traffic_rate.is_some_and(|rate| !rate.is_finite() || rate <= 0f32) {
+                errors.push(format!("Traffic rate override for stream with ID #{} on port {} has to be larger than zero.", stream.stream_id, setting.port));
+            }
+
+            if setting.stream_id == stream.stream_id && setting.traffic_rate.is_some() && *mode == GenerationMode::Poisson {
+                errors.push("Traffic rate overrides are not supported in Poisson generation mode.".to_owned());
             }
 
             // Check VxLAN
             if stream.vxlan && setting.vxlan.is_none() {
-                return Err(Error::new(format!("Stream with ID #{} is a VxLAN stream but no VxLAN settings provided.", stream.stream_id)));
+                errors.push(format!("Stream with ID #{} is a VxLAN stream but no VxLAN settings provided.", stream.stream_id));
             }
         }
     }
 
-    if streams.iter().map(|s| s.frame_size).collect::<Vec<u32>>().iter().sum::<u32>() > MAX_BUFFER_SIZE {
-        return Err(Error::new(format!("Sum of packet size too large. Maximal sum of packets size: {}B", MAX_BUFFER_SIZE)));
-    }
-
     if settings.is_empty() && *mode != GenerationMode::Analyze {
-        return Err(Error::new("No active streams provided."));
+        errors.push("No active streams provided.".to_owned());
     }
 
     if streams.is_empty() && *mode != GenerationMode::Analyze {
-        return Err(Error::new("No stream provided."));
+        errors.push("No stream provided.".to_owned());
+    }
+
+    // Validate the rate on each egress port against the speed of the port
+    if *mode != GenerationMode::Analyze {
+        let mut port_rates: BTreeMap<u32, f32> = BTreeMap::new();
+
+        for setting in settings.iter().filter(|s| s.active) {
+            if let Some(stream) = streams.iter().find(|s| s.stream_id == setting.stream_id) {
+                let rate = setting.traffic_rate.unwrap_or(stream.traffic_rate);
+                let rate = if *mode == GenerationMode::Mpps { (stream.frame_size + calculate_overhead(stream) + 20) as f32 * 8f32 * rate / 1000f32 } else { rate };
+
+                *port_rates.entry(setting.port).or_default() += rate;
+            }
+        }
+
+        for (port, rate) in port_rates {
+            if let Some(speed) = port_speeds.get(&port).filter(|speed| rate > **speed) {
+                errors.push(format!("Traffic rate of {:.2} Gbps on port {} exceeds the port speed of {} Gbps.", rate, port, speed));
+            }
+        }
+    }
+
+    // each rate override of a stream is generated by an additional application
+    let streams = match resolve_rate_overrides(streams.to_vec(), settings.to_vec()) {
+        Ok((streams, _)) => streams,
+        Err(P4TGError::Error { message }) => {
+            errors.push(message);
+            streams.to_vec()
+        }
+    };
+
+    // each copy of a stream for a rate override has its own packet in the buffer
+    if streams.iter().map(|s| s.frame_size).collect::<Vec<u32>>().iter().sum::<u32>() > MAX_BUFFER_SIZE {
+        errors.push(format!("Sum of packet size too large. Maximal sum of packets size: {}B", MAX_BUFFER_SIZE));
+    }
+
+    // Check that the required accuracy is reachable for each generated rate, including the rate overrides
+    if *mode != GenerationMode::Poisson && *mode != GenerationMode::Analyze {
+        for stream in streams.iter().filter(|s| s.required_accuracy.is_some_and(|a| a > 0f32 && a <= 100f32)) {
+            // preamble + inter frame gap (IFG) = 20 bytes
+            let frame_size = stream.frame_size + calculate_overhead(stream) + 20;
+            let rate = if *mode == GenerationMode::Mpps { frame_size as f32 * 8f32 * stream.traffic_rate / 1000f32 } else { stream.traffic_rate };
+
+            if let Err(P4TGError::Error { message }) = calculate_send_behaviour(frame_size, rate, stream.burst, stream.required_accuracy) {
+                errors.push(format!("Stream with ID #{}: {}", stream.stream_id, message));
+            }
+        }
     }
 
     // Validate max sending rate
@@ -90,9 +199,231 @@ pub fn validate_request(streams: &[Stream], settings: &[StreamSetting], mode: &G
     };
 
     if *mode != GenerationMode::Analyze && rate > if is_tofino2 {TG_MAX_RATE_TF2} else {TG_MAX_RATE} {
-        return Err(Error::new("Traffic rate in sum larger than maximal supported rate."))
+        errors.push("Traffic rate in sum larger than maximal supported rate.".to_owned());
+    }
+
+    errors
+}
+
+/// Validates the traffic filters of the analyze mode.
+/// Filters are only allowed in analyze mode and have to reference an RX port of the `tx_rx_mapping`.
+pub fn validate_analyze_filters(filters: &[AnalyzeFilter], mode: &GenerationMode, tx_rx_mapping: &HashMap<u32, Vec<u32>>) -> Result<(), Error> {
+    if !filters.is_empty() && *mode != GenerationMode::Analyze {
+        return Err(Error::new("Traffic filters are only allowed in analyze mode."));
+    }
+
+    for filter in filters {
+        if let Some(port) = filter.port.filter(|port| !tx_rx_mapping.values().flatten().any(|rx| rx == port)) {
+            return Err(Error::new(format!("Traffic filter port {} is not an RX port.", port)));
+        }
+
+        if filter.vlan_id.is_some_and(|id| id > 4095) {
+            return Err(Error::new("VLAN ID of a traffic filter has to be at most 4095."));
+        }
+
+        if filter.ip_src_prefix.is_some_and(|p| p > 32) || filter.ip_dst_prefix.is_some_and(|p| p > 32) {
+            return Err(Error::new("Prefix length of a traffic filter has to be at most 32."));
+        }
+
+        if filter.dscp.is_some_and(|dscp| dscp > 63) {
+            return Err(Error::new("DSCP of a traffic filter has to be at most 63."));
+        }
     }
 
     Ok(())
+}
+
+/// Validates the flow classification rules of the analyze mode.
+/// Rules are only allowed in analyze mode, need unique names and have to fit into the flow monitor table.
+pub fn validate_flow_rules(rules: &[FlowRule], mode: &GenerationMode, tx_rx_mapping: &HashMap<u32, Vec<u32>>) -> Result<(), Error> {
+    if !rules.is_empty() && *mode != GenerationMode::Analyze {
+        return Err(Error::new("Flow rules are only allowed in analyze mode."));
+    }
+
+    let mut rx_ports: Vec<&u32> = tx_rx_mapping.values().flatten().collect();
+    rx_ports.sort();
+    rx_ports.dedup();
+
+    let mut names = HashSet::new();
+    let mut entries = 0;
+
+    for rule in rules {
+        if rule.name.is_empty() {
+            return Err(Error::new("Name of a flow rule must not be empty."));
+        }
+
+        if !names.insert(&rule.name) {
+            return Err(Error::new(format!("Flow rule name {} is used multiple times.", rule.name)));
+        }
 
-}
\ No newline at end of file
+        if let Some(port) = rule.port.filter(|port| !rx_ports.contains(&port)) {
+            return Err(Error::new(format!("Flow rule port {} is not an RX port.", port)));
+        }
+
+        if rule.vlan_id.is_some_and(|id| id > 4095) {
+            return Err(Error::new("VLAN ID of a flow rule has to be at most 4095."));
+        }
+
+        if rule.ip_src_prefix.is_some_and(|p| p > 32) || rule.ip_dst_prefix.is_some_and(|p| p > 32) {
+            return Err(Error::new("Prefix length of a flow rule has to be at most 32."));
+        }
+
+        if (rule.src_port.is_some() || rule.dst_port.is_some()) && !matches!(rule.protocol, Some(6) | Some(17)) {
+            return Err(Error::new(format!("Flow rule {} matches on TCP/UDP ports but its protocol is neither TCP (6) nor UDP (17).", rule.name)));
+        }
+
+        entries += if rule.port.is_some() { 1 } else { rx_ports.len() };
+    }
+
+    if entries > FLOW_MONITOR_SIZE {
+        return Err(Error::new(format!("Flow rules require {} table entries, but at most {} are supported.", entries, FLOW_MONITOR_SIZE)));
+    }
+
+    Ok(())
+}
+
+/// Validates the sets of RX ports per TX port.
+/// Sets must not be empty and have to consist of traffic generation ports.
+/// An RX port of a set with multiple ports must not be used in any other set, and the RX recirculation ports
+/// of such a set have to be in the same pipe as they share the sequence number state in the data plane.
+///
+/// Several TX ports may send to the same RX port (incast), but not the same stream with the same rate,
+/// as the sequence number state is kept per RX port and app id in the data plane.
+pub fn validate_rx_port_groups(tx_rx_mapping: &HashMap<u32, Vec<u32>>, port_mapping: &HashMap<u32, PortMapping>, streams: &[Stream], settings: &[StreamSetting]) -> Result<(), Error> {
+    // number of sets that use an RX port
+    let mut usages: HashMap<u32, usize> = HashMap::new();
+
+    for (tx, rx_ports) in tx_rx_mapping {
+        if rx_ports.is_empty() {
+            return Err(Error::new(format!("Set of RX ports for TX port {} is empty.", tx)));
+        }
+
+        for rx in rx_ports {
+            if !port_mapping.contains_key(rx) {
+                return Err(Error::new(format!("RX port {} of TX port {} is not a traffic generation port.", rx, tx)));
+            }
+
+            *usages.entry(*rx).or_default() += 1;
+        }
+    }
+
+    for (tx, rx_ports) in tx_rx_mapping.iter().filter(|(_, rx_ports)| rx_ports.len() > 1) {
+        if let Some(rx) = rx_ports.iter().find(|rx| usages[rx] > 1) {
+            return Err(Error::new(format!("RX port {} of the set of RX ports of TX port {} is used multiple times.", rx, tx)));
+        }
+
+        // the pipe is encoded in the upper bits of the dev port
+        let pipes: HashSet<u32> = rx_ports.iter().map(|rx| port_mapping[rx].rx_recirculation >> 7).collect();
+
+        if pipes.len() > 1 {
+            return Err(Error::new(format!("RX recirculation ports of the RX ports of TX port {} are in different pipes.", tx)));
+        }
+    }
+
+    // settings of a stream with the same rate are generated by the same app id, see [resolve_rate_overrides]
+    // TX port per (RX port, stream id, rate)
+    let mut senders: HashMap<(u32, u8, u32), u32> = HashMap::new();
+
+    for setting in settings.iter().filter(|s| s.active) {
+        let Some(stream) = streams.iter().find(|s| s.stream_id == setting.stream_id) else {
+            continue;
+        };
+
+        let rate = setting.traffic_rate.unwrap_or(stream.traffic_rate);
+
+        for rx in tx_rx_mapping.get(&setting.port).into_iter().flatten() {
+            if let Some(tx) = senders.insert((*rx, stream.stream_id, rate.to_bits()), setting.port).filter(|tx| *tx != setting.port) {
+                return Err(Error::new(format!("Stream with ID #{} is sent on TX ports {} and {} to RX port {}. Several TX ports require different streams for the same RX port.", stream.stream_id, tx, setting.port, rx)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macaddr::MacAddr;
+
+    use crate::core::traffic_gen_core::test_util::{setting, stream};
+
+    /// Traffic generation ports 1 to 4 with their recirculation ports in pipe 0
+    fn port_mapping() -> HashMap<u32, PortMapping> {
+        (1..5).map(|port| (port, PortMapping { tx_recirculation: 10 + port, rx_recirculation: 20 + port, mac: MacAddr::from([0u8; 6]) })).collect()
+    }
+
+    fn buffer_error(errors: &[String]) -> bool {
+        errors.iter().any(|e| e.starts_with("Sum of packet size too large."))
+    }
+
+    #[test]
+    fn buffer_size_includes_rate_overrides() {
+        let streams = [stream(1, 4500, 10f32, 100)];
+
+        let mut overridden = setting(1, 2, true);
+        overridden.traffic_rate = Some(5f32);
+        let mut second = setting(1, 3, true);
+        second.traffic_rate = Some(20f32);
+
+        let errors = validation_errors(&streams, &[setting(1, 1, true), overridden.clone()], &GenerationMode::Cbr, false, &HashMap::new());
+        assert!(!buffer_error(&errors), "{:?}", errors);
+
+        let errors = validation_errors(&streams, &[setting(1, 1, true), overridden, second], &GenerationMode::Cbr, false, &HashMap::new());
+        assert!(buffer_error(&errors), "{:?}", errors);
+    }
+
+    #[test]
+    fn incast_with_different_streams() {
+        let streams = [stream(1, 64, 10f32, 100), stream(2, 64, 10f32, 100)];
+        let tx_rx_mapping = HashMap::from([(1, vec![3]), (2, vec![3])]);
+
+        assert!(validate_rx_port_groups(&tx_rx_mapping, &port_mapping(), &streams, &[setting(1, 1, true), setting(2, 2, true)]).is_ok());
+    }
+
+    #[test]
+    fn incast_of_the_same_stream_is_rejected() {
+        let streams = [stream(1, 64, 10f32, 100)];
+        let tx_rx_mapping = HashMap::from([(1, vec![3]), (2, vec![3])]);
+
+        assert!(validate_rx_port_groups(&tx_rx_mapping, &port_mapping(), &streams, &[setting(1, 1, true), setting(1, 2, true)]).is_err());
+
+        // inactive settings are not generated
+        assert!(validate_rx_port_groups(&tx_rx_mapping, &port_mapping(), &streams, &[setting(1, 1, true), setting(1, 2, false)]).is_ok());
+
+        // a rate override is generated by a different app id
+        let mut overridden = setting(1, 2, true);
+        overridden.traffic_rate = Some(5f32);
+        assert!(validate_rx_port_groups(&tx_rx_mapping, &port_mapping(), &streams, &[setting(1, 1, true), overridden]).is_ok());
+
+        // the same stream to different RX ports
+        let tx_rx_mapping = HashMap::from([(1, vec![3]), (2, vec![4])]);
+        assert!(validate_rx_port_groups(&tx_rx_mapping, &port_mapping(), &streams, &[setting(1, 1, true), setting(1, 2, true)]).is_ok());
+    }
+
+    #[test]
+    fn request_errors_include_port_groups_and_filters() {
+        let streams = vec![stream(1, 64, 10f32, 100)];
+        let settings = vec![setting(1, 1, true), setting(1, 2, true)];
+        let tx_rx_mapping = HashMap::from([(1, vec![3]), (2, vec![3])]);
+
+        let payload = TrafficGenData {
+            mode: GenerationMode::Cbr,
+            stream_settings: settings.clone(),
+            streams: streams.clone(),
+            port_tx_rx_mapping: HashMap::from([(1, 3), (2, 3)]),
+            port_tx_rx_groups: None,
+            duration: None,
+            name: None,
+            all_test: None,
+            analyze_filters: Some(vec![AnalyzeFilter { port: None, vlan_id: Some(1), ip_src: None, ip_src_prefix: None, ip_dst: None, ip_dst_prefix: None, dscp: None }]),
+            flow_rules: None
+        };
+
+        let errors = request_errors(&payload, &streams, &settings, &tx_rx_mapping, &port_mapping(), false, &HashMap::new());
+
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].starts_with("Stream with ID #1 is sent on TX ports"));
+        assert_eq!(errors[1], "Traffic filters are only allowed in analyze mode.");
+    }
+}
@@ -18,9 +18,10 @@
  */
 pub mod server;
 mod online;
+mod devices;
 pub mod statistics;  
 mod traffic_gen;
-mod ports;
+pub mod ports;
 mod tables;
 
 mod helper;
@@ -28,6 +29,8 @@ mod reset;
 mod restart;
 
 mod config;
+mod monitoring;
+mod frame_types;
 mod docs;
 
 mod multiple_traffic_gen;
@@ -41,12 +44,16 @@ pub use profiles::{run_profile, rfc_results, abort_profile};
 mod rfc_tests;
 
 pub use online::online;
+pub use devices::devices;
 pub use statistics::statistics;
 pub use traffic_gen::traffic_gen;
 pub use traffic_gen::configure_traffic_gen;
+pub use traffic_gen::plan_traffic_gen;
 pub use traffic_gen::stop_traffic_gen;
 pub use ports::ports;
 pub use ports::add_port;
 pub use reset::reset;
 pub use restart::restart;
-pub use config::config;
\ No newline at end of file
+pub use config::config;
+pub use monitoring::{monitoring, configure_monitoring};
+pub use frame_types::{frame_types, add_frame_type, remove_frame_type};
\ No newline at end of file
@@ -0,0 +1,191 @@
+/* Copyright 2022-present University of Tuebingen, Chair of Communication Networks
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
+ */
+
+use std::sync::Arc;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::api::server::Error;
+use crate::AppState;
+use crate::core::{FrameSizeBin, FrameSizeMonitor};
+use crate::core::statistics::HistogramConfig;
+use crate::core::traffic_gen_core::const_definitions::{MAX_HISTOGRAM_BINS, MAX_MONITORING_PACKET_INTERVAL, MAX_SAMPLE_RATE, MIN_MONITORING_PACKET_INTERVAL};
+
+/// Monitoring settings of the data plane.
+/// Unset fields are not changed on POST.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MonitoringSettings {
+    /// Interval in ns in which monitoring packets are generated.
+    /// This is the granularity of the rate, packet loss and out of order statistics.
+    #[schema(example = 500000000)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) monitoring_interval: Option<u32>,
+    /// Number of RTT samples per second and port. 0 disables RTT sampling.
+    #[schema(example = 800)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rtt_sample_rate: Option<u32>,
+    /// Number of IAT samples per second and port. Only used in sample mode. 0 disables IAT sampling.
+    #[schema(example = 800)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) iat_sample_rate: Option<u32>,
+    /// Percentiles of the RTT that are reported in the statistics, e.g., [50, 99, 99.9].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rtt_percentiles: Option<Vec<f64>>,
+    /// Bucket layout of the RTT histogram that is reported in the statistics.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rtt_histogram: Option<HistogramConfig>,
+    /// Frame size bins of the frame size statistics. Changing the bins resets the frame size counters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) frame_size_bins: Option<Vec<FrameSizeBin>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/monitoring",
+    responses(
+    (status = 200,
+    description = "Returns the current monitoring settings.",
+    body = MonitoringSettings))
+)]
+/// Returns the current monitoring settings
+pub async fn monitoring(State(state): State<Arc<AppState>>) -> Response {
+    (StatusCode::OK, Json(current_settings(&state).await)).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/monitoring",
+    request_body = MonitoringSettings,
+    responses(
+    (status = 200,
+    description = "Applies the monitoring settings and returns the current monitoring settings.",
+    body = MonitoringSettings),
+    (status = 400,
+    description = "Returned when a setting is out of range."))
+)]
+/// Changes the monitoring packet interval, the RTT / IAT sample rates and the frame size bins at runtime.
+/// The timer of the monitoring packet generation, the meter tables and the frame size monitor table are reprogrammed.
+pub async fn configure_monitoring(State(state): State<Arc<AppState>>, Json(payload): Json<MonitoringSettings>) -> Response {
+    if let Some(interval) = payload.monitoring_interval {
+        if !(MIN_MONITORING_PACKET_INTERVAL..=MAX_MONITORING_PACKET_INTERVAL).contains(&interval) {
+            return (StatusCode::BAD_REQUEST, Json(Error::new(format!("Monitoring interval has to be between {} ns and {} ns.", MIN_MONITORING_PACKET_INTERVAL, MAX_MONITORING_PACKET_INTERVAL)))).into_response();
+        }
+    }
+
+    if payload.rtt_sample_rate.is_some_and(|rate| rate > MAX_SAMPLE_RATE) || payload.iat_sample_rate.is_some_and(|rate| rate > MAX_SAMPLE_RATE) {
+        return (StatusCode::BAD_REQUEST, Json(Error::new(format!("Sample rate has to be at most {} samples per second.", MAX_SAMPLE_RATE)))).into_response();
+    }
+
+    if payload.rtt_percentiles.iter().flatten().any(|p| !(*p > 0f64 && *p <= 100f64)) {
+        return (StatusCode::BAD_REQUEST, Json(Error::new("Percentiles have to be in the range (0, 100]."))).into_response();
+    }
+
+    if let Some(histogram) = &payload.rtt_histogram {
+        if histogram.num_bins == 0 || histogram.num_bins > MAX_HISTOGRAM_BINS {
+            return (StatusCode::BAD_REQUEST, Json(Error::new(format!("Number of histogram buckets has to be between 1 and {}.", MAX_HISTOGRAM_BINS)))).into_response();
+        }
+
+        if histogram.min >= histogram.max {
+            return (StatusCode::BAD_REQUEST, Json(Error::new("Lower bound of the histogram has to be smaller than the upper bound."))).into_response();
+        }
+
+        if histogram.logarithmic && histogram.min == 0 {
+            return (StatusCode::BAD_REQUEST, Json(Error::new("Lower bound of a logarithmic histogram has to be larger than 0."))).into_response();
+        }
+    }
+
+    if let Some(bins) = &payload.frame_size_bins {
+        if let Err(err) = FrameSizeMonitor::validate_bins(bins, state.port_mapping.len()) {
+            return (StatusCode::BAD_REQUEST, Json(Error::new(err))).into_response();
+        }
+    }
+
+    let result = {
+        let tg = &mut state.traffic_generator.lock().await;
+        let switch = &*state.switch.read().await;
+
+        let interval = match payload.monitoring_interval {
+            Some(interval) => tg.set_monitoring_interval(switch, interval).await,
+            None => Ok(())
+        };
+
+        let rate_monitor = &mut state.rate_monitor.lock().await;
+
+        if interval.is_ok() {
+            rate_monitor.monitoring_interval = tg.monitoring_interval;
+        }
+
+        let rtt = match payload.rtt_sample_rate {
+            Some(rate) => {
+                rate_monitor.rtt_sample_rate = rate;
+                rate_monitor.init_rtt_meter(switch).await
+            }
+            None => Ok(())
+        };
+
+        let iat = match payload.iat_sample_rate {
+            Some(rate) => {
+                rate_monitor.iat_sample_rate = rate;
+                rate_monitor.init_iat_meter(switch, state.sample_mode).await
+            }
+            None => Ok(())
+        };
+
+        if let Some(percentiles) = &payload.rtt_percentiles {
+            rate_monitor.rtt_percentiles = percentiles.clone();
+        }
+
+        if let Some(histogram) = &payload.rtt_histogram {
+            rate_monitor.rtt_histogram = histogram.clone();
+        }
+
+        interval.and(rtt).and(iat)
+    };
+
+    let result = match (result, &payload.frame_size_bins) {
+        (Ok(_), Some(bins)) => {
+            let frame_size_monitor = &mut state.frame_size_monitor.lock().await;
+            frame_size_monitor.set_bins(bins.clone());
+            frame_size_monitor.configure(&*state.switch.read().await).await
+        }
+        (result, _) => result
+    };
+
+    match result {
+        Ok(_) => (StatusCode::OK, Json(current_settings(&state).await)).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
+    }
+}
+
+async fn current_settings(state: &AppState) -> MonitoringSettings {
+    let monitoring_interval = state.traffic_generator.lock().await.monitoring_interval;
+    let rate_monitor = state.rate_monitor.lock().await;
+
+    MonitoringSettings {
+        monitoring_interval: Some(monitoring_interval),
+        rtt_sample_rate: Some(rate_monitor.rtt_sample_rate),
+        iat_sample_rate: Some(rate_monitor.iat_sample_rate),
+        rtt_percentiles: Some(rate_monitor.rtt_percentiles.clone()),
+        rtt_histogram: Some(rate_monitor.rtt_histogram.clone()),
+        frame_size_bins: Some(state.frame_size_monitor.lock().await.bins()),
+    }
+}
@@ -6,12 +6,15 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Json, Response};
 use serde::de::DeserializeOwned;
 use crate::api::helper::validate::validate_request;
+use crate::core::traffic_gen_core::planner::port_speeds;
+use crate::core::traffic_gen_core::helper::rx_port_groups;
 use std::time::SystemTime;
 use crate::api::server::Error;
 use crate::AppState;
+use crate::error::{P4TGError, TrafficGenError};
 use crate::core::traffic_gen_core::types::*;
 use crate::api::traffic_gen::stop_traffic_gen;
-use crate::api::statistics::{Statistics, statistics, time_statistics};
+use crate::api::statistics::{LossEvents, Statistics, loss_events, statistics, time_statistics};
 use axum::extract::Query;
 use crate::core::statistics::TimeStatistic;
 use axum::body::{self};
@@ -194,6 +197,9 @@ pub async fn reset_and_start_experiment(state_clone: Arc<AppState>, payload_clon
     let mut collected_time_statistics = state_clone.multi_test_state.collected_time_statistics.lock().await; 
     collected_time_statistics.clear();
 
+    let mut collected_loss_events = state_clone.multi_test_state.collected_loss_events.lock().await;
+    collected_loss_events.clear();
+
     let mut multiple_traffic_generators = state_clone.multi_test_state.multiple_traffic_generators.lock().await;
     multiple_traffic_generators.clear(); 
     multiple_traffic_generators.extend(payload_clone.clone());
@@ -222,6 +228,16 @@ pub async fn save_statistics(state_clone: Arc<AppState>, i: usize) -> Result<(),
         return Err(format!("Failed to retrieve time statistics for test {}", i + 1));
     }
 
+    // Using loss_events endpoint to retrieve the loss events of the current test
+    let loss_events_response = loss_events(State(Arc::clone(&state_clone))).await;
+    if let Ok(mut events) = parse_response::<LossEvents>(loss_events_response).await {
+        // Storing loss events in AppState
+        events.previous_loss_events = None;
+        state_clone.multi_test_state.collected_loss_events.lock().await.push(events);
+    } else {
+        return Err(format!("Failed to retrieve loss events for test {}", i + 1));
+    }
+
     Ok(())
 }
 
@@ -261,34 +277,32 @@ async fn start_traffic_gen(State(state): State<Arc<AppState>>, payload: Json<Tra
     let active_stream_ids: Vec<u8> = active_stream_settings.iter().map(|s| s.stream_id).collect();
     let active_streams: Vec<Stream> = payload.streams.clone().into_iter().filter(|s| active_stream_ids.contains(&s.stream_id)).collect();
 
-    // Poisson traffic is only allowed to have a single stream
-    if payload.mode == GenerationMode::Poisson && active_streams.len() != 1 {
-        return (StatusCode::BAD_REQUEST, Json(Error::new("Poisson generation mode only allows for one stream."))).into_response()
-    }
-
-    // no streams should be generated in monitor/analyze mode
-    if payload.mode == GenerationMode::Analyze && !active_streams.is_empty() {
-        return (StatusCode::BAD_REQUEST, Json(Error::new("No stream definition in analyze mode allowed."))).into_response();
-    }
-
     // contains the mapping of Send->Receive ports
-    // required for analyze mode
-    let port_mapping = &payload.port_tx_rx_mapping;
+    // required for analyze mode and the accounting of RX port sets
+    let port_mapping = &rx_port_groups(&payload.port_tx_rx_mapping, payload.port_tx_rx_groups.as_ref());
+
+    // rates are validated against the current speed of the ports
+    let port_speeds = match port_speeds(&state.pm, &*state.switch.read().await).await {
+        Ok(speeds) => speeds,
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
+    };
 
     // validate request
-    match validate_request(&active_streams, &active_stream_settings, &payload.mode, tg.is_tofino2) {
-        Ok(_) => {},
-        Err(e) => return (StatusCode::BAD_REQUEST, Json(e)).into_response()
+    if let Err(e) = validate_request(&payload, &active_streams, &active_stream_settings, port_mapping, &state.port_mapping, tg.is_tofino2, &port_speeds) {
+        return (StatusCode::BAD_REQUEST, Json(e)).into_response();
     }
 
-    match tg.start_traffic_generation(&state, active_streams, payload.mode, active_stream_settings, port_mapping).await {
+    match tg.start_traffic_generation(&state, active_streams, payload.mode, active_stream_settings, port_mapping, payload.analyze_filters.as_deref().unwrap_or_default(), payload.flow_rules.as_deref().unwrap_or_default()).await {
         Ok(streams) => {
             // store the settings for synchronization between multiple
             // GUI clients
             tg.port_mapping = payload.port_tx_rx_mapping.clone();
+            tg.port_groups = payload.port_tx_rx_groups.clone();
             tg.stream_settings = payload.stream_settings.clone();
             tg.streams = payload.streams.clone();
             tg.mode = payload.mode;
+            tg.analyze_filters = payload.analyze_filters.clone();
+            tg.flow_rules = payload.flow_rules.clone();
 
             // experiment starts now
             // these values are used to show how long the experiment is running at the GUI
@@ -298,6 +312,7 @@ async fn start_traffic_gen(State(state): State<Arc<AppState>>, payload: Json<Tra
             info!("Traffic generation started.");
             (StatusCode::OK, Json(streams)).into_response()
         }
-        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
+        Err(TrafficGenError::Invalid(P4TGError::Error { message })) => (StatusCode::BAD_REQUEST, Json(Error::new(message))).into_response(),
+        Err(TrafficGenError::Switch(err)) => (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
     }
 }
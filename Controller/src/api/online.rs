@@ -25,7 +25,7 @@ use schemars::JsonSchema;
 use serde::Serialize;
 use crate::AppState;
 
-#[derive(Serialize, JsonSchema)]
+#[derive(Serialize, JsonSchema, Clone)]
 pub enum Asic {
     Tofino1,
     Tofino2
@@ -40,12 +40,15 @@ pub struct Online {
 }
 
 /// Online endpoint
+/// Returns 503 with status "switch disconnected" while the connection to the switch is lost.
 pub async fn online(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Online>) {
-    (StatusCode::OK, Json(Online {status: "online".to_owned(),
+    let connected = *state.connected.lock().await;
+
+    (if connected {StatusCode::OK} else {StatusCode::SERVICE_UNAVAILABLE},
+     Json(Online {status: if connected {"online".to_owned()} else {"switch disconnected".to_owned()},
         version: env!("CARGO_PKG_VERSION").parse().unwrap(),
         asic: if state.tofino2 {Asic::Tofino2} else {Asic::Tofino1},
         loopback: state.loopback_mode
     }
     ))
 }
-
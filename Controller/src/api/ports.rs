@@ -21,6 +21,8 @@ use std::sync::Arc;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::{Json, IntoResponse, Response};
+use rbfrt::error::RBFRTError;
+use rbfrt::util::PortManager;
 use rbfrt::util::port_manager::{Loopback, Port};
 use serde::{Deserialize, Serialize};
 use crate::api::docs;
@@ -35,6 +37,24 @@ pub struct PortConfiguration {
     auto_neg: rbfrt::util::port_manager::AutoNegotiation
 }
 
+impl PortConfiguration {
+    /// Creates the port manager request of the port configuration.
+    pub(crate) fn request(&self, pm: &PortManager, loopback_mode: bool) -> Result<Port, RBFRTError> {
+        let (port, channel) = pm.frontpanel_port(self.pid)?;
+
+        let mut req = Port::new(port, channel)
+            .speed(self.speed.clone())
+            .fec(self.fec.clone())
+            .auto_negotiation(self.auto_neg.clone());
+
+        if loopback_mode {
+            req = req.loopback(Loopback::BF_LPBK_MAC_NEAR);
+        }
+
+        Ok(req)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PortStats {
     pid: u32
@@ -59,7 +79,7 @@ pub struct ArpReply {
 )]
 pub async fn ports(State(state): State<Arc<AppState>>) -> Response {
     let pm = &state.pm;
-    let switch = &state.switch;
+    let switch = &*state.switch.read().await;
 
     match pm.get_ports(switch).await {
         Ok(ports) => Json(ports).into_response(),
@@ -70,19 +90,13 @@ pub async fn ports(State(state): State<Arc<AppState>>) -> Response {
 pub async fn add_port(State(state): State<Arc<AppState>>, payload: Json<PortConfiguration>) -> Response {
     let pm = &state.pm;
 
-    match pm.frontpanel_port(payload.pid) {
-        Ok((port, channel)) => {
-            let mut req = Port::new(port , channel)
-                .speed(payload.speed.clone())
-                .fec(payload.fec.clone())
-                .auto_negotiation(payload.auto_neg.clone());
-
-            if state.loopback_mode {
-                req = req.loopback(Loopback::BF_LPBK_MAC_NEAR);
-            }
-
-            match pm.update_port(&state.switch, &req).await {
+    match payload.request(pm, state.loopback_mode) {
+        Ok(req) => {
+            match pm.update_port(&*state.switch.read().await, &req).await {
                 Ok(_) => {
+                    // replayed on a reconnect to the switch
+                    state.port_configurations.lock().await.insert(payload.pid, payload.0.clone());
+
                     StatusCode::CREATED.into_response()
                 }
                 Err(err) => {
@@ -101,7 +115,7 @@ pub async fn arp_reply(State(state): State<Arc<AppState>>, payload: Json<ArpRepl
 
     match mapping.get(&payload.pid) {
         Some(port) => {
-            match &state.arp_handler.modify_arp(&state.switch, port, payload.arp_reply).await {
+            match &state.arp_handler.modify_arp(&*state.switch.read().await, port, payload.arp_reply).await {
                 Ok(_) => {
                     let port = &state.pm.frontpanel_port(payload.pid);
 
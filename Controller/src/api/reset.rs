@@ -39,22 +39,25 @@ use crate::core::traffic_gen_core::types::Reset;
     ))
 )]
 pub async fn reset(State(state): State<Arc<AppState>>) -> Response {
-    let switch = &state.switch;
+    let switch = &*state.switch.read().await;
     let frame_size = state.frame_size_monitor.lock().await.on_reset(switch).await;
     let frame_type = state.frame_type_monitor.lock().await.on_reset(switch).await;
+    let flow = state.flow_monitor.lock().await.on_reset(switch).await;
     let rate = state.rate_monitor.lock().await.on_reset(switch).await;
     let mut collected_statistics = state.multi_test_state.collected_statistics.lock().await;
     let mut collected_time_statistics = state.multi_test_state.collected_time_statistics.lock().await;
+    let mut collected_loss_events = state.multi_test_state.collected_loss_events.lock().await;
 
-    if frame_size.is_ok() && frame_type.is_ok() && rate.is_ok() {
+    if frame_size.is_ok() && frame_type.is_ok() && flow.is_ok() && rate.is_ok() {
         // Clear the statistics
         collected_statistics.clear();    
         collected_time_statistics.clear();
+        collected_loss_events.clear();
     
         (StatusCode::OK, Json(Reset { message: "Reset complete".to_owned() })).into_response()
     }
     else {
-        for error in [frame_size, frame_type, rate] {
+        for error in [frame_size, frame_type, flow, rate] {
             if error.is_err() {
                 return (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:?}", error.err().unwrap())))).into_response();
             }
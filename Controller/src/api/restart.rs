@@ -25,7 +25,9 @@ use axum::extract::State;
 use std::sync::Arc;
 use std::time::SystemTime;
 use crate::AppState;
+use crate::error::{P4TGError, TrafficGenError};
 use crate::core::traffic_gen_core::types::*;
+use crate::core::traffic_gen_core::helper::rx_port_groups;
 use crate::api::docs::traffic_gen::EXAMPLE_POST_1_RESPONSE;
 use crate::api::server::Error;
 
@@ -40,7 +42,7 @@ use crate::api::server::Error;
     example = json!(*EXAMPLE_POST_1_RESPONSE)
     ),
     (status = 400,
-    description = "No traffic generation is running that could be restarted."))
+    description = "No traffic generation is running that could be restarted or the traffic generation can not be generated with the current settings."))
 )]
 /// Restarts the current traffic generation
 pub async fn restart(State(state): State<Arc<AppState>>) -> Response {
@@ -56,15 +58,18 @@ pub async fn restart(State(state): State<Arc<AppState>>) -> Response {
     let active_stream_ids: Vec<u8> = active_stream_settings.iter().map(|s| s.stream_id).collect();
     let active_streams: Vec<Stream> = tg.streams.clone().into_iter().filter(|s| active_stream_ids.contains(&s.stream_id)).collect();
     let mode = tg.mode;
-    let mapping = tg.port_mapping.clone();
+    let mapping = rx_port_groups(&tg.port_mapping, tg.port_groups.as_ref());
+    let analyze_filters = tg.analyze_filters.clone().unwrap_or_default();
+    let flow_rules = tg.flow_rules.clone().unwrap_or_default();
 
-    match tg.start_traffic_generation(&state, active_streams, mode, active_stream_settings, &mapping).await {
+    match tg.start_traffic_generation(&state, active_streams, mode, active_stream_settings, &mapping, &analyze_filters, &flow_rules).await {
         Ok(streams) => {
             info!("Traffic generation restarted.");
             state.experiment.lock().await.start = SystemTime::now();
             state.experiment.lock().await.running = true;
             (StatusCode::OK, Json(streams)).into_response()
         }
-        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
+        Err(TrafficGenError::Invalid(P4TGError::Error { message })) => (StatusCode::BAD_REQUEST, Json(Error::new(message))).into_response(),
+        Err(TrafficGenError::Switch(err)) => (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
     }
 }
\ No newline at end of file
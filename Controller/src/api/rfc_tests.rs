@@ -5,8 +5,10 @@ use crate::api::multiple_traffic_gen::{
 };
 use crate::api::statistics::{Statistics, statistics};
 use crate::AppState;
+use crate::core::loss_events::{DisruptionReport, DisruptionThresholds};
 
-use rbfrt::util::port_manager::Speed;
+use crate::core::traffic_gen_core::const_definitions::{TG_MAX_RATE, TG_MAX_RATE_TF2};
+use crate::core::traffic_gen_core::planner::port_speeds;
 
 use std::sync::Arc;
 use axum::extract::State;
@@ -14,15 +16,10 @@ use axum::http::StatusCode;
 use crate::api::server::Error;
 use axum::response::{IntoResponse, Json, Response};
 use log::{error, info, warn};
-use tokio::time::{sleep, Duration};
-use std::time::Instant;
+use tokio::time::Duration;
 use std::collections::BTreeMap;
 
 
-use rbfrt::table;
-use rbfrt::table::{MatchValue, ToBytes};
-use std::collections::HashMap;
-
 
 const FRAME_SIZES: [u32; 5] = [64, 128, 512, 1024, 1518];
 const IAT_PRECISION: u16 = 1;
@@ -40,6 +37,9 @@ pub async fn throughput_test(
 
     let mut frame_rate_results = BTreeMap::new();
 
+    // the exponential search is bounded by the line rate of the sending ports
+    let max_rate = line_rate(&state, &payload).await?;
+
     for &frame_size in FRAME_SIZES.iter() {
         let mut test_payload = payload.clone();
         test_payload.streams[0].burst = RATE_PRECISION;
@@ -55,6 +55,7 @@ pub async fn throughput_test(
             Arc::clone(&state),
             test_payload.clone(),
             initial_tx_rate,
+            max_rate,
         ).await?;
 
         warn!("Interval of Exponential search for {} Bytes: [{}, {}]", frame_size, lower_bound, upper_bound);
@@ -176,6 +177,7 @@ async fn exponential_search_for_max_rate(
     state: Arc<AppState>,
     mut test_payload: TrafficGenData,
     initial_tx_rate: f32,
+    max_rate: f32,
 ) -> Result<(f32, f32), Response> {
     let mut k = 0;
     let mut current_rate = initial_tx_rate;
@@ -186,10 +188,10 @@ async fn exponential_search_for_max_rate(
     while k < max_iterrations {
         let test_rate = initial_tx_rate * 2f32.powi(k);
 
-        if test_rate > 100.0 {
+        if test_rate > max_rate {
             let lower_bound = initial_tx_rate * 2f32.powi(k - 1);
-            info!("Test rate exceeds 100 Gbps, returning interval [{}, 100]", lower_bound);
-            return Ok((lower_bound, 100.0));
+            info!("Test rate exceeds {} Gbps, returning interval [{}, {}]", max_rate, lower_bound, max_rate);
+            return Ok((lower_bound, max_rate));
         }
 
         info!("Exponential search iteration {}: testing rate {}", k, test_rate);
@@ -255,6 +257,7 @@ pub async fn latency_test(State(state): State<Arc<AppState>>, Json(payload): Jso
     info!("Starting latency test for all frame sizes");
 
     let mut latency_results = BTreeMap::new();
+    let mut stream_latency_results: BTreeMap<u32, BTreeMap<u32, f64>> = BTreeMap::new();
 
     for &frame_size in FRAME_SIZES.iter() {
         let frame_rate = {
@@ -276,7 +279,11 @@ pub async fn latency_test(State(state): State<Arc<AppState>>, Json(payload): Jso
         save_tg(Arc::clone(&state), adjusted_payload.clone(), format!("Latency - {} Bytes", frame_size)).await;
 
         let mut abort_rx = create_and_store_abort_sender(Arc::clone(&state)).await;
-        let mut rtt_values_for_frame_size = Vec::new();
+        // sum and number of the RTTs of all runs
+        let mut rtt_sum_for_frame_size = 0f64;
+        let mut rtt_count_for_frame_size = 0u64;
+        // app id -> (sum, number) of the RTTs of all runs
+        let mut app_rtts_for_frame_size: BTreeMap<u32, (f64, u64)> = BTreeMap::new();
 
         for i in 0..10 { 
             match start_traffic_gen_with_duration(Arc::clone(&state), adjusted_payload.clone(), i, Some(10.0), &mut abort_rx).await {
@@ -284,9 +291,16 @@ pub async fn latency_test(State(state): State<Arc<AppState>>, Json(payload): Jso
                     info!("Successfully completed traffic generation {}", i + 1);
                     
                     // RTT-Value from rtt_storage 
-                    let rtt_storage = state.rate_monitor.lock().await.rtt_storage.clone();
-                    for (_, rtts) in rtt_storage.iter() {
-                        rtt_values_for_frame_size.extend(rtts.iter().copied());
+                    let rate_monitor = state.rate_monitor.lock().await;
+                    for rtts in rate_monitor.rtt_storage.values() {
+                        rtt_sum_for_frame_size += rtts.mean() * rtts.count() as f64;
+                        rtt_count_for_frame_size += rtts.count();
+                    }
+
+                    for (app_id, rtts) in rate_monitor.app_rtt_storage.values().flatten() {
+                        let (sum, count) = app_rtts_for_frame_size.entry(*app_id).or_default();
+                        *sum += rtts.mean() * rtts.count() as f64;
+                        *count += rtts.count();
                     }
                 },
                 Err(err) => {
@@ -304,8 +318,8 @@ pub async fn latency_test(State(state): State<Arc<AppState>>, Json(payload): Jso
         }
 
 
-        if !rtt_values_for_frame_size.is_empty() {
-            let overall_mean_ns = calculate_mean(&rtt_values_for_frame_size);
+        if rtt_count_for_frame_size > 0 {
+            let overall_mean_ns = rtt_sum_for_frame_size / rtt_count_for_frame_size as f64;
             let overall_mean_us = overall_mean_ns / 1000.0;
             let one_way_latency = overall_mean_us / 2.0;
             latency_results.insert(frame_size, one_way_latency);
@@ -315,8 +329,17 @@ pub async fn latency_test(State(state): State<Arc<AppState>>, Json(payload): Jso
             latency_results.insert(frame_size, f64::NAN);
         }
         
+        // one-way latency per stream in µs
+        let stream_latency: BTreeMap<u32, f64> = app_rtts_for_frame_size.iter()
+            .filter(|(_, (_, count))| *count > 0)
+            .map(|(app_id, (sum, count))| (*app_id, sum / *count as f64 / 1000.0 / 2.0))
+            .collect();
+
+        stream_latency_results.insert(frame_size, stream_latency);
+
         let mut test_result = state.multi_test_state.rfc_results.lock().await;
         test_result.latency = Some(latency_results.clone());
+        test_result.stream_latency = Some(stream_latency_results.clone());
     }
 
     let result = (StatusCode::OK, Json(latency_results));
@@ -335,22 +358,8 @@ pub async fn frame_loss_rate_test(State(state): State<Arc<AppState>>, Json(paylo
     let mut abort_rx = create_and_store_abort_sender(Arc::clone(&state)).await;
     let mut results = BTreeMap::new();
 
-    let ports = state.pm.get_ports(&state.switch).await.map_err(|err| {
-        error!("Failed to get ports: {:?}", err);
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("Failed to get ports: {:?}", err)))).into_response()
-    })?;
-
-    // Determine the maximum speed from the sending ports
-    let max_speed = ports.iter().map(|port| match port.get_speed() {
-        Speed::BF_SPEED_1G => 1.0,
-        Speed::BF_SPEED_10G => 10.0,
-        Speed::BF_SPEED_20G => 20.0,
-        Speed::BF_SPEED_25G => 25.0,
-        Speed::BF_SPEED_40G => 40.0,
-        Speed::BF_SPEED_50G => 50.0,
-        Speed::BF_SPEED_100G => 100.0,
-        Speed::BF_SPEED_400G => 400.0,
-    }).max_by(|a, b| a.partial_cmp(b).unwrap()).unwrap_or(1.0);
+    // Determine the line rate from the speed of the sending ports
+    let max_speed = line_rate(&state, &payload).await?;
 
     for &frame_size in &FRAME_SIZES {
         let mut test_payload = payload.clone();
@@ -443,7 +452,6 @@ pub async fn reset_test(State(state): State<Arc<AppState>>, Json(payload): Json<
     info!("Starting reset test for the minimum frame size (64 Bytes)");
 
     let duration = Duration::from_secs(120);
-    let interval = Duration::from_millis(100); 
 
     let frame_size = 64;
     let mut result = 0.0; // Default value 
@@ -467,76 +475,30 @@ pub async fn reset_test(State(state): State<Arc<AppState>>, Json(payload): Json<
     save_tg(Arc::clone(&state), adjusted_payload.clone(), format!("Reset - {} Bytes", frame_size)).await;
 
     let state_clone = Arc::clone(&state);
-    let abort_rx = create_and_store_abort_sender(state_clone.clone()).await;
-
-    // Abort Receiver for both tasks
-    let mut tg_abort_rx = abort_rx.clone();
-    let mut monitor_abort_rx = abort_rx.clone();
-    
-    // Traffic gen task
-    let tg_task = tokio::spawn(async move {
-        start_traffic_gen_with_duration(
-            state_clone,
-            adjusted_payload,
-            0,
-            Some(duration.as_secs_f64()),
-            &mut tg_abort_rx
-        ).await
-    });
-
-    // Monitoring task
-    let state_clone = Arc::clone(&state);
-    let monitor_task = tokio::spawn(async move {
-        monitor_packet_loss(&state_clone, duration, interval, &mut monitor_abort_rx).await
-    });
-
-    let (tg_result, monitor_result) = tokio::join!(tg_task, monitor_task);
-
-    // Check results of traffic generation
-    match tg_result {
-        Ok(Ok(())) => info!("Traffic generation completed successfully"),
-        Ok(Err(err)) => {
-            error!("Error starting traffic generator: {}", err);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("Error starting traffic generator: {}", err)))).into_response());
-        },
-        Err(err) => {
-            error!("Traffic generation task panicked: {:?}", err);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new("Traffic generation task panicked".to_string()))).into_response());
-        }
+    let mut abort_rx = create_and_store_abort_sender(state_clone.clone()).await;
+
+    // the outages are logged as loss events during the traffic generation
+    let tg_result = start_traffic_gen_with_duration(
+        state_clone,
+        adjusted_payload,
+        0,
+        Some(duration.as_secs_f64()),
+        &mut abort_rx
+    ).await;
+
+    if let Err(err) = tg_result {
+        error!("Error starting traffic generator: {}", err);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("Error starting traffic generator: {}", err)))).into_response());
     }
 
-    // Check results of monitoring
-    match monitor_result {
-        Ok(Ok((duration_a, duration_b))) => {
-            if duration_a.is_none() {
-                info!("No significant packet loss detected within 120 seconds for frame size {}.", frame_size);
-            } else {
-                let duration_a = duration_a.unwrap();
-                info!("Duration A for frame size {}: {:?}", frame_size, duration_a);
-
-                if duration_b.is_none() {
-                    info!("No packet loss recovery detected for frame size {}.", frame_size);
-                } else {
-                    let duration_b = duration_b.unwrap();
-                    info!("Duration B for frame size {}: {:?}", frame_size, duration_b);
+    let report = DisruptionReport::new(state.rate_monitor.lock().await.loss_events.events(), &DisruptionThresholds::default());
 
-                    // Recovery interval
-                    let recovery_time = duration_b - duration_a;
-                    let recovery_time_secs = recovery_time.as_secs_f64();
-                    info!("Recovery time after reset for frame size {}: {:.3} seconds", frame_size, recovery_time_secs);
-
-                    result = recovery_time_secs;
-                }
-            }
-        },
-        Ok(Err(err)) => {
-            error!("Error in packet loss monitoring for frame size {}: {:?}", frame_size, err);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new("Error in packet loss monitoring".to_string()))).into_response());
-        },
-        Err(err) => {
-            error!("Packet loss monitoring task panicked for frame size {}: {:?}", frame_size, err);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new("Packet loss monitoring task panicked".to_string()))).into_response());
-        }
+    if report.outages.is_empty() {
+        info!("No packet loss detected within {} seconds for frame size {}.", duration.as_secs(), frame_size);
+    } else {
+        // Recovery interval of the longest outage
+        result = report.max_disruption / 1000.0;
+        info!("Recovery time after reset for frame size {}: {:.3} seconds ({} outages)", frame_size, result, report.outages.len());
     }
 
     // Save statistics after the test
@@ -553,131 +515,6 @@ pub async fn reset_test(State(state): State<Arc<AppState>>, Json(payload): Json<
     Ok((StatusCode::OK, Json(result)))
 }
 
-// monitors if packets are received within monitoring_duration
-// returns time point A and B if no packets are received for a certain time
-async fn monitor_packet_loss(
-    state: &Arc<AppState>,
-    monitoring_duration: Duration,
-    interval: Duration,
-    abort_rx: &mut tokio::sync::watch::Receiver<()>
-) -> Result<(Option<Instant>, Option<Instant>), Response> {
-    
-    let mut previous_packets_received = wait_for_first_packet(state, interval, abort_rx).await;
-
-    if previous_packets_received == 0 {
-        info!("Monitoring aborted during wait for first packet.");
-        return Ok((None, None));
-    }
-    
-    let start_time = Instant::now();
-    let mut time_point_a: Option<Instant> = None;
-    let mut time_point_b: Option<Instant>;
-    let mut longest_time_point_a: Option<Instant> = None;
-    let mut longest_time_point_b: Option<Instant> = None;
-    let mut max_duration_a_to_b: Option<Duration> = None;
-
-    while Instant::now().duration_since(start_time) < monitoring_duration {
-        tokio::select! {
-            _ = sleep(interval) => {},
-            _ = abort_rx.changed() => {
-                info!("Abort signal received, stopping monitoring.");
-                return Ok((None, None));
-            }
-        }
-
-        info!("{:?} - {:?}", Instant::now().duration_since(start_time), monitoring_duration);
-        
-
-        let total_packets_received = get_total_received_packets(state).await;
-
-            
-        if total_packets_received.abs_diff(previous_packets_received) == 0 {
-            info!("NO PACKETS RECEIVED!!!!!!!!!!!!");
-        }
-        info!("Differenz {}", total_packets_received.abs_diff(previous_packets_received));
-
-
-        if total_packets_received == previous_packets_received && time_point_a.is_none() {
-            // Time point A if no new packets are received
-            time_point_a = Some(Instant::now());
-            info!("Time Point A recorded: {:?}", time_point_a);
-        } else if total_packets_received > previous_packets_received && time_point_a.is_some() {
-            // Time point B if packets are received after A
-            time_point_b = Some(Instant::now());
-            info!("Time Point B recorded: {:?}", time_point_b);
-
-            // Calculate the time span between A and B
-            if let Some(a) = time_point_a {
-                let duration = time_point_b.unwrap().duration_since(a);
-
-                info!("Duration between A and B: {:?}", duration);
-
-                // Check if this is the longest observed duration
-                if max_duration_a_to_b.is_none() || max_duration_a_to_b.unwrap() < duration {
-                    max_duration_a_to_b = Some(duration);
-                    longest_time_point_a = time_point_a;
-                    longest_time_point_b = time_point_b;
-                    info!("New maximum duration between A and B: {:?}", max_duration_a_to_b);
-                }
-            }
-
-            time_point_a = None;
-        } 
-
-        previous_packets_received = total_packets_received;
-    }
-
-    Ok((longest_time_point_a, longest_time_point_b))
-}
-
-
-
-// Waits for the first packet to be received
-async fn wait_for_first_packet(
-    state: &Arc<AppState>,
-    interval: Duration,
-    abort_rx: &mut tokio::sync::watch::Receiver<()>
-) -> u64 {
-    // Wait until the experiment is running
-    loop {
-        tokio::select! {
-            _ = tokio::time::sleep(interval) => {
-                let running = state.experiment.lock().await.running;
-                if running {
-                    break;
-                }
-                info!("Experiment not running yet, waiting...");
-            }
-            _ = abort_rx.changed() => {
-                info!("Abort signal received, stopping wait for first packet.");
-                return 0; // You can return a default value or handle it differently
-            }
-        }
-    }
-
-    // Wait until the first frame is received
-    loop {
-        tokio::select! {
-            _ = tokio::time::sleep(interval) => {
-                let total_packets_received = get_total_received_packets(state).await;
-                if total_packets_received > 0 {
-                    info!("First packet received: {}", total_packets_received);
-                    return total_packets_received;
-                }
-                info!("No packets yet, waiting...");
-            }
-            _ = abort_rx.changed() => {
-                info!("Abort signal received, stopping wait for first packet.");
-                return 0; // Again, handle this however you see fit
-            }
-        }
-    }
-}
-
-
-
-
-
 pub async fn handle_test_result<T: std::fmt::Debug>(result: Result<(StatusCode, Json<T>), Response>, test_name: &str) -> Result<(), ()> {
     match result {
         Ok((status, Json(result))) if status == StatusCode::OK => {
@@ -701,6 +538,7 @@ pub async fn reset_results(state: Arc<AppState>) {
     *res = TestResult {
         throughput: None,
         latency: None,
+        stream_latency: None,
         frame_loss_rate: None,
         reset: None,
         running: false,
@@ -732,58 +570,35 @@ pub async fn reset_collected_statistics(state_clone: Arc<AppState>) {
     let mut collected_time_statistics = state_clone.multi_test_state.collected_time_statistics.lock().await; 
     collected_time_statistics.clear();
 
+    let mut collected_loss_events = state_clone.multi_test_state.collected_loss_events.lock().await;
+    collected_loss_events.clear();
+
     let mut multiple_traffic_generators = state_clone.multi_test_state.multiple_traffic_generators.lock().await;
     multiple_traffic_generators.clear(); 
 }
 
 
-fn round_to_three_places(value: f64) -> f64 {
-    (value * 1000.0).round() / 1000.0
-}
-
-fn calculate_mean(values: &[u64]) -> f64 {
-    let sum: u64 = values.iter().sum();
-    sum as f64 / values.len() as f64
-}
-
-
-async fn get_total_received_packets(state: &Arc<AppState>) -> u64 {
-    const FRAME_SIZE_MONITOR: &str = "egress.frame_size_monitor";
-
-    let request = table::Request::new(FRAME_SIZE_MONITOR);
-    let sync = table::Request::new(FRAME_SIZE_MONITOR).operation(table::TableOperation::SyncCounters);
+/// Returns the line rate in Gbps of a test, i.e., the speed of the slowest sending port.
+/// Percent-of-line-rate values of the tests refer to this rate.
+async fn line_rate(state: &Arc<AppState>, payload: &TrafficGenData) -> Result<f32, Response> {
+    let port_speeds = port_speeds(&state.pm, &*state.switch.read().await).await.map_err(|err| {
+        error!("Failed to get ports: {:?}", err);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("Failed to get ports: {:?}", err)))).into_response()
+    })?;
 
+    let max_rate = if state.tofino2 { TG_MAX_RATE_TF2 } else { TG_MAX_RATE };
 
-    let entries = {
-        let switch = &state.switch;
-        if switch.execute_operation(sync).await.is_err() {
-            warn! {"Encountered error while synchronizing {}.", FRAME_SIZE_MONITOR};
-        }
-        switch.get_table_entry(request).await.unwrap_or_else(|err| {
-            warn! {"Error while retrieving {} table. Error: {}", FRAME_SIZE_MONITOR, format!("{:#?}", err)};
-            vec![]
-        })
-    };
-
-    let mut total_packets_received: u64 = 0;
-    let rx_mapping = state.port_mapping.iter().map(|(_, mapping)| (mapping.rx_recirculation, mapping)).collect::<HashMap<_, _>>();
+    Ok(payload.stream_settings.iter()
+        .filter(|s| s.active)
+        .filter_map(|s| port_speeds.get(&s.port).copied())
+        .min_by(|a, b| a.total_cmp(b))
+        .unwrap_or(max_rate)
+        .min(max_rate))
+}
 
-    for entry in entries {
-        if let Some(egress_port) = entry.match_key.get("eg_intr_md.egress_port") {
-            let port_val = egress_port.get_exact_value().to_u32();
+fn round_to_three_places(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
 
-            if rx_mapping.contains_key(&port_val) {
-                if let MatchValue::RangeValue { lower_bytes: _, higher_bytes: _ } = entry.match_key.get("pkt_len").unwrap() {
-                    let count = entry.action_data.iter()
-                        .find(|action| action.get_name() == "$COUNTER_SPEC_PKTS")
-                        .map(|action| action.get_data().to_u128())
-                        .unwrap_or(0);
-                    total_packets_received += count as u64;
-                }
-            }
-        }
-    }
 
-    total_packets_received
-}
 
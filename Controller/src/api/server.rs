@@ -17,8 +17,9 @@
  * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
  */
 
-use std::sync::Arc;
 use std::env;
+use std::future::Future;
+use std::sync::Arc;
 
 use log::{info, warn};
 use serde::Serialize;
@@ -31,15 +32,17 @@ use utoipa::{openapi::security::{ApiKey, ApiKeyValue, SecurityScheme}, Modify, O
 use utoipa_swagger_ui::SwaggerUi;
 
 use tower_http::cors::{Any, CorsLayer};
-use crate::api::{add_port, config, configure_traffic_gen, online, ports, reset, restart, statistics, stop_traffic_gen, traffic_gen, configure_multiple_traffic_gen, run_profile, rfc_results, abort_profile};
+use crate::api::{add_frame_type, add_port, config, configure_monitoring, frame_types, remove_frame_type, configure_traffic_gen, devices, monitoring, online, ports, reset, restart, statistics, stop_traffic_gen, traffic_gen, plan_traffic_gen, configure_multiple_traffic_gen, run_profile, rfc_results, abort_profile};
 
 
 use crate::api::helper::serve_static_files::{serve_index, static_path};
 use crate::api::ports::arp_reply;
-use crate::api::statistics::time_statistics;
+use crate::api::statistics::{disruption, loss_events, time_statistics};
 use crate::api::tables::tables;
 use crate::AppState;
 use crate::api::tables;
+use crate::api::devices::Device;
+use crate::api::online::Asic;
 
 use crate::core::traffic_gen_core::types::*;
 
@@ -49,12 +52,20 @@ use crate::core::traffic_gen_core::types::*;
     paths(
         traffic_gen::traffic_gen,
         traffic_gen::configure_traffic_gen,
+        traffic_gen::plan_traffic_gen,
         traffic_gen::stop_traffic_gen,
         tables::tables,
         statistics::statistics,
+        statistics::loss_events,
+        statistics::disruption,
         restart::restart,
         reset::reset,
-        ports::ports
+        ports::ports,
+        crate::api::monitoring::monitoring,
+        crate::api::monitoring::configure_monitoring,
+        crate::api::frame_types::frame_types,
+        crate::api::frame_types::add_frame_type,
+        crate::api::frame_types::remove_frame_type
     ),
     components(
         schemas(TrafficGenData,
@@ -63,6 +74,13 @@ use crate::core::traffic_gen_core::types::*;
         StreamSetting,
         Stream,
         EmptyResponse,
+        TrafficGenPlan,
+        AnalyzeFilter,
+        FlowRule,
+        PacketBufferEntry,
+        HeaderRewriteEntry,
+        GenerationPortLoad,
+        EgressPortLoad,
         Reset,
         Ethernet,
         IPv4,
@@ -70,12 +88,25 @@ use crate::core::traffic_gen_core::types::*;
         VxLAN,
         MPLSHeader,
         tables::TableDescriptor,
+        crate::api::monitoring::MonitoringSettings,
         statistics::Statistics,
+        statistics::LossEvents,
+        crate::core::loss_events::LossEvent,
+        statistics::Disruption,
+        crate::core::loss_events::DisruptionReport,
         crate::core::statistics::RangeCount,
         crate::core::statistics::RangeCountValue,
         crate::core::statistics::TypeCount,
+        crate::core::statistics::FlowCounter,
+        crate::core::statistics::RxGroupStatistics,
         crate::core::statistics::IATStatistics,
         crate::core::statistics::RTTStatistics,
+        crate::core::statistics::HistogramConfig,
+        crate::core::statistics::HistogramBin,
+        crate::core::statistics::ReorderStatistics,
+        crate::core::statistics::ReorderDensityBin,
+        crate::core::FrameSizeBin,
+        crate::core::FrameTypeClassifier,
         crate::core::statistics::IATValues
         )
     ),
@@ -119,10 +150,8 @@ impl IntoResponse for Error {
 }
 
 
-pub async fn start_api_server(state: Arc<AppState>) {
-
-    let port = env::var("P4TG_PORT").unwrap_or("8000".to_owned()).parse().unwrap_or(8000);
-
+/// Router for the REST API of a device
+fn api_router(state: Arc<AppState>) -> Router {
     let cors = CorsLayer::new()
         // allow `GET` and `POST` when accessing the resource
         .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
@@ -130,13 +159,14 @@ pub async fn start_api_server(state: Arc<AppState>) {
         .allow_origin(Any)
         .allow_headers(Any);
 
-
-    // Router for the REST API
-    let api_router = Router::new()
+    Router::new()
         .route("/online", get(online))
         .route("/statistics", get(statistics))
         .route("/time_statistics", get(time_statistics))
+        .route("/statistics/loss_events", get(loss_events))
+        .route("/statistics/disruption", get(disruption))
         .route("/trafficgen", get(traffic_gen).post(configure_traffic_gen).delete(stop_traffic_gen))
+        .route("/trafficgen/plan", post(plan_traffic_gen))
         .route("/reset", get(reset))
         .route("/restart", get(restart))
         .route("/ports", get(ports))
@@ -144,15 +174,53 @@ pub async fn start_api_server(state: Arc<AppState>) {
         .route("/ports/arp", post(arp_reply))
         .route("/tables", get(tables))
         .route("/config", get(config))
+        .route("/monitoring", get(monitoring).post(configure_monitoring))
+        .route("/frame_types", get(frame_types).post(add_frame_type).delete(remove_frame_type))
         .route("/multiple_trafficgen", post(configure_multiple_traffic_gen))
         .route("/profiles", get(rfc_results).post(run_profile).delete(abort_profile))
         .layer(cors)
-        .with_state(Arc::clone(&state));
+        .with_state(state)
+}
+
+/// Starts the REST API server.
+/// The first device is served at `/api`, each device `i` is additionally served at `/api/devices/i`.
+/// The server shuts down gracefully once `shutdown` completes.
+pub async fn start_api_server(states: Vec<Arc<AppState>>, shutdown: impl Future<Output = ()> + Send + 'static) {
+
+    let port = env::var("P4TG_PORT").unwrap_or("8000".to_owned()).parse().unwrap_or(8000);
+
+    let mut device_list = vec![];
+
+    for (index, state) in states.iter().enumerate() {
+        let config = state.config.lock().await;
+        let connection = config.connection();
+
+        device_list.push(Device {
+            index,
+            name: config.name(),
+            host: connection.host,
+            port: connection.port,
+            device_id: connection.device_id,
+            asic: if state.tofino2 {Asic::Tofino2} else {Asic::Tofino1}
+        });
+    }
+
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::OPTIONS])
+        .allow_origin(Any)
+        .allow_headers(Any);
 
     // Router for the static configuration gui
-    let app = Router::new()
+    let mut app = Router::new()
         .merge(SwaggerUi::new("/api/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .nest_service("/api", api_router)
+        .route("/api/devices", get(devices).layer(cors).with_state(Arc::new(device_list)))
+        .nest_service("/api", api_router(Arc::clone(&states[0])));
+
+    for (index, state) in states.iter().enumerate() {
+        app = app.nest_service(&format!("/api/devices/{}", index), api_router(Arc::clone(state)));
+    }
+
+    let app = app
         .route("/", get(serve_index)) // create react routing endpoints
         .route("/home", get(serve_index))
         .route("/ports", get(serve_index))
@@ -167,7 +235,7 @@ pub async fn start_api_server(state: Arc<AppState>) {
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
         .await.unwrap_or_else(|_| panic!("Unable to listen on 0.0.0.0:{}", port));
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app).with_graceful_shutdown(shutdown).await.unwrap();
 }
 
 
@@ -27,7 +27,10 @@ use axum::response::{Json, IntoResponse, Response};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use crate::AppState;
-use crate::core::statistics::{IATStatistics, IATValues, RangeCount, RTTStatistics, TimeStatistic, TypeCount};
+use crate::core::statistics::{FlowCounter, HistogramConfig, IATStatistics, IATValues, RangeCount, ReorderStatistics, RTTStatistics, RxGroupStatistics, TimeStatistic, TypeCount};
+use crate::core::loss_events::{DisruptionReport, DisruptionThresholds, LossEvent};
+use crate::core::sample_storage::{DelayVariation, SampleStorage};
+use crate::core::traffic_gen_core::const_definitions::PDV_PERCENTILE;
 
 use crate::api::{docs, helper};
 
@@ -53,16 +56,45 @@ pub struct Statistics {
     /// L2 receive rate per stream and port.
     /// The number corresponds to the app_id in the Stream description.
     pub(crate) app_rx_l2: HashMap<u32, HashMap<u32, f64>>,
+    /// Packet and byte counters and rates per RX port and flow in analyze mode.
+    /// The key corresponds to the name of the flow rule.
+    #[serde(default)]
+    pub(crate) flow_rx: HashMap<u32, HashMap<String, FlowCounter>>,
     /// Statistics what kind of packets have been received per port
     pub(crate) frame_type_data: HashMap<u32, TypeCount>,
     /// Statistics of the inter arrival times per port.
     pub(crate) iats: HashMap<u32, IATStatistics>,
     /// Statistics of the round trip times per port.
     pub(crate) rtts: HashMap<u32, RTTStatistics>,
+    /// Statistics of the round trip times per stream and port.
+    /// The number corresponds to the app_id in the Stream description.
+    #[serde(default)]
+    pub(crate) app_rtts: HashMap<u32, HashMap<u32, RTTStatistics>>,
     /// Number of lost packets per port.
     pub(crate) packet_loss: HashMap<u32, u64>,
     /// Number of out of order packets per port.
     pub(crate) out_of_order: HashMap<u32, u64>,
+    /// Number of lost packets per stream and RX port.
+    /// The number corresponds to the app_id in the Stream description.
+    #[serde(default)]
+    pub(crate) app_packet_loss: HashMap<u32, HashMap<u32, u64>>,
+    /// Number of out of order packets per stream and RX port.
+    /// The number corresponds to the app_id in the Stream description.
+    #[serde(default)]
+    pub(crate) app_out_of_order: HashMap<u32, HashMap<u32, u64>>,
+    /// Duplicates, reordering extent (RFC 4737) and reorder density (RFC 5236) per RX port.
+    /// Out of order packets in `out_of_order` do not include duplicates.
+    #[serde(default)]
+    pub(crate) reordering: HashMap<u32, ReorderStatistics>,
+    /// Duplicates, reordering extent and reorder density per stream and RX port.
+    /// The number corresponds to the app_id in the Stream description.
+    #[serde(default)]
+    pub(crate) app_reordering: HashMap<u32, HashMap<u32, ReorderStatistics>>,
+    /// Statistics of the sets of RX ports per TX port.
+    /// Packet loss and out of order packets of a set are accounted on its first RX port
+    /// and are therefore also contained in `packet_loss` and `out_of_order` of that port.
+    #[serde(default)]
+    pub(crate) rx_groups: HashMap<u32, RxGroupStatistics>,
     /// Elapsed time since the traffic generation has started in seconds.
     pub(crate) elapsed_time: u32,
     /// Save previous statistics, where the key is the test number of the statistics. 
@@ -112,10 +144,17 @@ pub async fn statistics(State(state): State<Arc<AppState>>) -> Response {
         rx_rate_l2: Default::default(),
         app_tx_l2: Default::default(),
         app_rx_l2: Default::default(),
+        flow_rx: Default::default(),
         iats: Default::default(),
         rtts: Default::default(),
+        app_rtts: Default::default(),
         packet_loss: Default::default(),
         out_of_order: Default::default(),
+        app_packet_loss: Default::default(),
+        app_out_of_order: Default::default(),
+        reordering: Default::default(),
+        app_reordering: Default::default(),
+        rx_groups: Default::default(),
         elapsed_time: 0,
         previous_statistics: previous_statistics_map,
     };
@@ -123,50 +162,55 @@ pub async fn statistics(State(state): State<Arc<AppState>>) -> Response {
 
     stats.frame_size = frame_size_monitor.lock().await.statistics.frame_size.clone();
     stats.frame_type_data = frame_type_monitor.lock().await.statistics.frame_type_data.clone();
+    stats.flow_rx = state.flow_monitor.lock().await.statistics.flow_rx.clone();
 
     let monitor_statistics =  rate_monitor.lock().await.statistics.clone();
 
-    let rtts = rate_monitor.lock().await.rtt_storage.clone();
-
-    let mut rtt_stats = HashMap::new();
-
-    for (port, rtt_samples) in &rtts {
-        let mean = helper::simple_stats::average(rtt_samples);
-        let std = helper::simple_stats::std(rtt_samples);
-
-        let stats = RTTStatistics {
-            mean,
-            min: *rtt_samples.iter().min().unwrap_or(&0) as u32,
-            max: *rtt_samples.iter().max().unwrap_or(&0) as u32,
-            current: *rtt_samples.iter().last().unwrap_or(&0) as u32,
-            jitter: std,
-            n: rtt_samples.len() as u32,
-        };
-
-        rtt_stats.insert(*port, stats);
-    }
+    // statistics are computed on the cached sorted snapshot of the bounded sample storage
+    let (rtt_stats, app_rtt_stats) = {
+        let mut guard = rate_monitor.lock().await;
+        let rate_monitor = &mut *guard;
+
+        let rtt_stats: HashMap<u32, RTTStatistics> = rate_monitor.rtt_storage.iter_mut()
+            .map(|(port, rtt_samples)| {
+                let variation = rate_monitor.rtt_variation.get(port).cloned().unwrap_or_default();
+                (*port, rtt_statistics(rtt_samples, &variation, &rate_monitor.rtt_percentiles, &rate_monitor.rtt_histogram))
+            })
+            .collect();
+
+        let app_rtt_stats: HashMap<u32, HashMap<u32, RTTStatistics>> = rate_monitor.app_rtt_storage.iter_mut()
+            .map(|(port, apps)| {
+                (*port, apps.iter_mut().map(|(app_id, rtt_samples)| {
+                    let variation = rate_monitor.app_rtt_variation.get(port).and_then(|v| v.get(app_id)).cloned().unwrap_or_default();
+                    (*app_id, rtt_statistics(rtt_samples, &variation, &rate_monitor.rtt_percentiles, &rate_monitor.rtt_histogram))
+                }).collect())
+            })
+            .collect();
+
+        (rtt_stats, app_rtt_stats)
+    };
 
     if state.sample_mode {
         let mut iats = HashMap::new();
-        let tx_stats = &mut state.rate_monitor.lock().await.tx_iat_storage.clone();
-        let rx_stats = &mut state.rate_monitor.lock().await.rx_iat_storage.clone();
+        let rate_monitor = rate_monitor.lock().await;
+        let empty = SampleStorage::new(0);
 
         for port in state.port_mapping.keys() {
-            let tx_iats = tx_stats.entry(*port).or_default();
-            let rx_iats = rx_stats.entry(*port).or_default();
+            let tx_iats = rate_monitor.tx_iat_storage.get(port).unwrap_or(&empty);
+            let rx_iats = rate_monitor.rx_iat_storage.get(port).unwrap_or(&empty);
 
             let iat_stats = IATStatistics {
                 tx: IATValues {
-                    mean: helper::simple_stats::average(tx_iats) as f32,
-                    std: Some(helper::simple_stats::std(tx_iats) as f32),
+                    mean: tx_iats.mean() as f32,
+                    std: Some(tx_iats.std() as f32),
                     mae: 0.0,
-                    n: tx_iats.len() as u32,
+                    n: tx_iats.count() as u32,
                 },
                 rx: IATValues {
-                    mean: helper::simple_stats::average(rx_iats) as f32,
-                    std: Some(helper::simple_stats::std(rx_iats) as f32),
+                    mean: rx_iats.mean() as f32,
+                    std: Some(rx_iats.std() as f32),
                     mae: 0.0,
-                    n: rx_iats.len() as u32,
+                    n: rx_iats.count() as u32,
                 },
             };
 
@@ -181,6 +225,7 @@ pub async fn statistics(State(state): State<Arc<AppState>>) -> Response {
     }
 
     stats.rtts = rtt_stats;
+    stats.app_rtts = app_rtt_stats;
     stats.tx_rate_l1 = monitor_statistics.tx_rate_l1.clone();
     stats.rx_rate_l1 = monitor_statistics.rx_rate_l1.clone();
     stats.tx_rate_l2 = monitor_statistics.tx_rate_l2.clone();
@@ -189,6 +234,13 @@ pub async fn statistics(State(state): State<Arc<AppState>>) -> Response {
     stats.app_rx_l2 = monitor_statistics.app_rx_l2.clone();
     stats.packet_loss = monitor_statistics.packet_loss.clone();
     stats.out_of_order = monitor_statistics.out_of_order.clone();
+    stats.app_packet_loss = monitor_statistics.app_packet_loss.clone();
+    stats.app_out_of_order = monitor_statistics.app_out_of_order.clone();
+    stats.reordering = monitor_statistics.reordering.clone();
+    stats.app_reordering = monitor_statistics.app_reordering.clone();
+    stats.rx_groups = state.traffic_generator.lock().await.port_groups.iter().flatten()
+        .map(|(tx, rx_ports)| (*tx, RxGroupStatistics::new(rx_ports, &monitor_statistics)))
+        .collect();
     stats.elapsed_time = {
         let experiment = state.experiment.lock().await;
         if experiment.running {
@@ -202,9 +254,131 @@ pub async fn statistics(State(state): State<Arc<AppState>>) -> Response {
     (StatusCode::OK, Json(stats)).into_response()
 }
 
+/// Computes the RTT statistics of the `rtt_samples`, including the `percentiles` and a histogram.
+/// Min, max, mean and jitter cover all samples, percentiles, PDV and the histogram the retained samples.
+fn rtt_statistics(rtt_samples: &mut SampleStorage, variation: &DelayVariation, percentiles: &[f64], histogram: &HistogramConfig) -> RTTStatistics {
+    let sorted = rtt_samples.sorted_samples();
+
+    RTTStatistics {
+        mean: rtt_samples.mean(),
+        min: rtt_samples.min() as u32,
+        max: rtt_samples.max() as u32,
+        current: rtt_samples.last() as u32,
+        jitter: rtt_samples.std(),
+        n: rtt_samples.count() as u32,
+        rfc3550_jitter: variation.jitter(),
+        ipdv_mean: variation.ipdv_mean(),
+        ipdv_min: variation.ipdv_min(),
+        ipdv_max: variation.ipdv_max(),
+        pdv: helper::simple_stats::percentile(&sorted, PDV_PERCENTILE).saturating_sub(rtt_samples.min()) as u32,
+        percentiles: percentiles.iter().map(|p| (p.to_string(), helper::simple_stats::percentile(&sorted, *p) as u32)).collect(),
+        histogram: helper::simple_stats::histogram(&sorted, histogram)
+    }
+}
+
+/// Loss events of the streams
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct LossEvents {
+    /// Loss events of the current traffic generation in the order of their start
+    pub(crate) events: Vec<LossEvent>,
+    /// Save previous loss events, where the key is the test number of the loss events.
+    /// Skip serializing if there are no previous loss events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) previous_loss_events: Option<BTreeMap<u32, LossEvents>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/statistics/loss_events",
+    responses(
+        (status = 200,
+        description = "Returns the loss events of the streams.",
+        body = LossEvents
+        ))
+)]
+/// Returns the loss events of the streams, i.e., when loss started and ended,
+/// how many frames were lost and the outage duration computed from the frame rate
+pub async fn loss_events(State(state): State<Arc<AppState>>) -> Response {
+    let events = state.rate_monitor.lock().await.loss_events.events().to_vec();
+    let collected_loss_events = state.multi_test_state.collected_loss_events.lock().await.clone();
+
+    // Single test no previous loss events
+    let mut previous_loss_events_map: Option<BTreeMap<u32, LossEvents>> = None;
+
+    if !collected_loss_events.is_empty() {
+        previous_loss_events_map = Some(collected_loss_events.into_iter().enumerate().map(|(index, events)| ((index + 1) as u32, events)).collect());
+    }
+
+    (StatusCode::OK, Json(LossEvents { events, previous_loss_events: previous_loss_events_map })).into_response()
+}
+
+/// Service disruption of the streams
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct Disruption {
+    /// Service disruption of the current traffic generation
+    pub(crate) report: DisruptionReport,
+    /// Service disruption of previous tests, where the key is the test number.
+    /// Skip serializing if there are no previous tests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) previous_reports: Option<BTreeMap<u32, DisruptionReport>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/statistics/disruption",
+    params(
+        ("min_lost_frames" = Option<u64>, Query, description = "Loss events with fewer lost frames are not counted as outage"),
+        ("min_outage" = Option<f64>, Query, description = "Loss events with a shorter disruption in ms are not counted as outage"),
+        ("max_disruption" = Option<f64>, Query, description = "Maximal allowed disruption of an outage in ms")
+    ),
+    responses(
+        (status = 200,
+        description = "Returns the service disruption of the streams.",
+        body = Disruption
+        ))
+)]
+/// Returns the service disruption of the streams, i.e., every outage with its duration
+/// derived from the lost frames and the frame rate as well as from the timestamps,
+/// and the total and maximal disruption
+pub async fn disruption(State(state): State<Arc<AppState>>, Query(thresholds): Query<DisruptionThresholds>) -> Response {
+    let report = DisruptionReport::new(state.rate_monitor.lock().await.loss_events.events(), &thresholds);
+    let collected_loss_events = state.multi_test_state.collected_loss_events.lock().await.clone();
+
+    // Single test no previous reports
+    let mut previous_reports_map: Option<BTreeMap<u32, DisruptionReport>> = None;
+
+    if !collected_loss_events.is_empty() {
+        previous_reports_map = Some(collected_loss_events.iter().enumerate().map(|(index, events)| ((index + 1) as u32, DisruptionReport::new(&events.events, &thresholds))).collect());
+    }
+
+    (StatusCode::OK, Json(Disruption { report, previous_reports: previous_reports_map })).into_response()
+}
+
+/// Resolution of the keys of the time statistics
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeResolution {
+    /// Elapsed time in seconds, the last value of each second is kept
+    #[default]
+    S,
+    /// Elapsed time in ms, aligned to the monitoring interval
+    Ms
+}
+
 #[derive(Debug, Deserialize, Default)]
 pub struct Params {
     limit: Option<usize>,
+    resolution: Option<TimeResolution>,
+}
+
+/// Converts the keys of a time series from ms to the `resolution` and keeps every `step`-th element.
+/// `interval` is the monitoring interval in ms that the keys are aligned to.
+fn sample_time_series<V>(series: BTreeMap<u32, V>, resolution: TimeResolution, interval: u32, step: usize) -> BTreeMap<u32, V> {
+    match resolution {
+        TimeResolution::S => series.into_iter().map(|(key, value)| (key / 1000, value)).collect::<BTreeMap<u32, V>>()
+            .into_iter().filter(|elem| elem.0 % (step as u32) == 0).collect(),
+        TimeResolution::Ms => series.into_iter().filter(|elem| elem.0 % interval.saturating_mul(step as u32) == 0).collect()
+    }
 }
 
 pub async fn time_statistics(State(state): State<Arc<AppState>>, Query(params): Query<Params>) -> Response {
@@ -220,9 +394,17 @@ pub async fn time_statistics(State(state): State<Arc<AppState>>, Query(params):
     
 
     let limit = params.limit.unwrap_or(usize::MAX);
+    let resolution = params.resolution.unwrap_or_default();
 
-    // we typically have as many elements as elapsed seconds
-    let elements =  state.experiment.lock().await.start.elapsed().unwrap_or(Duration::from_secs(0)).as_secs() as usize;
+    // the time series are stored in ms and aligned to the monitoring interval
+    // we typically have as many elements as elapsed seconds, or one element per monitoring interval in ms resolution
+    let interval = (state.rate_monitor.lock().await.monitoring_interval / 1_000_000).max(1);
+    let elapsed = state.experiment.lock().await.start.elapsed().unwrap_or(Duration::from_secs(0));
+
+    let elements = match resolution {
+        TimeResolution::S => elapsed.as_secs() as usize,
+        TimeResolution::Ms => elapsed.as_millis() as usize / interval as usize
+    };
 
     let step = {
         if limit < elements {
@@ -234,39 +416,56 @@ pub async fn time_statistics(State(state): State<Arc<AppState>>, Query(params):
     };
 
     // get every ratio-nth element
-    let tx: BTreeMap<u32, BTreeMap<u32, f64>> = stats.tx_rate_l1.clone()
-        .into_iter()
-        .map(|v|
-            (v.0, v.1.into_iter().filter(|elem| elem.0 % (step as u32) == 0).collect())).collect();
-
-    let rx: BTreeMap<u32, BTreeMap<u32, f64>> = stats.rx_rate_l1.clone()
-        .into_iter()
-        .map(|v|
-            (v.0, v.1.into_iter().filter(|elem| elem.0 % (step as u32) == 0).collect())).collect();
-
-    let packet_loss: BTreeMap<u32, BTreeMap<u32, u64>> = stats.packet_loss.clone()
-        .into_iter()
-        .map(|v|
-            (v.0, v.1.into_iter().filter(|elem| elem.0 % (step as u32) == 0).collect())).collect();
-
-    let out_of_order: BTreeMap<u32, BTreeMap<u32, u64>> = stats.out_of_order.clone()
-        .into_iter()
-        .map(|v|
-            (v.0, v.1.into_iter().filter(|elem| elem.0 % (step as u32) == 0).collect())).collect();
-
-    let rtt: BTreeMap<u32, BTreeMap<u32, u64>> = stats.rtt.clone()
-        .into_iter()
-        .map(|v|
-            (v.0, v.1.into_iter().filter(|elem| elem.0 % (step as u32) == 0).collect())).collect();            
+    let tx: BTreeMap<u32, BTreeMap<u32, f64>> = stats.tx_rate_l1.into_iter().map(|v| (v.0, sample_time_series(v.1, resolution, interval, step))).collect();
+    let rx: BTreeMap<u32, BTreeMap<u32, f64>> = stats.rx_rate_l1.into_iter().map(|v| (v.0, sample_time_series(v.1, resolution, interval, step))).collect();
+    let packet_loss: BTreeMap<u32, BTreeMap<u32, u64>> = stats.packet_loss.into_iter().map(|v| (v.0, sample_time_series(v.1, resolution, interval, step))).collect();
+    let out_of_order: BTreeMap<u32, BTreeMap<u32, u64>> = stats.out_of_order.into_iter().map(|v| (v.0, sample_time_series(v.1, resolution, interval, step))).collect();
+    let app_packet_loss: BTreeMap<u32, BTreeMap<u32, BTreeMap<u32, u64>>> = stats.app_packet_loss.into_iter()
+        .map(|v| (v.0, v.1.into_iter().map(|app| (app.0, sample_time_series(app.1, resolution, interval, step))).collect())).collect();
+    let app_out_of_order: BTreeMap<u32, BTreeMap<u32, BTreeMap<u32, u64>>> = stats.app_out_of_order.into_iter()
+        .map(|v| (v.0, v.1.into_iter().map(|app| (app.0, sample_time_series(app.1, resolution, interval, step))).collect())).collect();
+    let duplicates: BTreeMap<u32, BTreeMap<u32, u64>> = stats.duplicates.into_iter().map(|v| (v.0, sample_time_series(v.1, resolution, interval, step))).collect();
+    let app_duplicates: BTreeMap<u32, BTreeMap<u32, BTreeMap<u32, u64>>> = stats.app_duplicates.into_iter()
+        .map(|v| (v.0, v.1.into_iter().map(|app| (app.0, sample_time_series(app.1, resolution, interval, step))).collect())).collect();
+    let max_extent: BTreeMap<u32, BTreeMap<u32, u32>> = stats.max_extent.into_iter().map(|v| (v.0, sample_time_series(v.1, resolution, interval, step))).collect();
+    let rtt: BTreeMap<u32, BTreeMap<u32, u64>> = stats.rtt.into_iter().map(|v| (v.0, sample_time_series(v.1, resolution, interval, step))).collect();
 
     let stats = TimeStatistic {
         tx_rate_l1: tx,
         rx_rate_l1: rx,
         packet_loss,
         out_of_order,
+        app_packet_loss,
+        app_out_of_order,
+        duplicates,
+        app_duplicates,
+        max_extent,
         rtt,
         previous_time_statistics: Some(previous_time_statistics_map),
     };
 
     (StatusCode::OK, Json(stats)).into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_series_resolution() {
+        // 500 ms monitoring interval
+        let series: BTreeMap<u32, u64> = (0..8).map(|i| (i * 500, i as u64)).collect();
+
+        let seconds = sample_time_series(series.clone(), TimeResolution::S, 500, 1);
+        assert_eq!(seconds, BTreeMap::from([(0, 1), (1, 3), (2, 5), (3, 7)]));
+
+        let seconds = sample_time_series(series.clone(), TimeResolution::S, 500, 2);
+        assert_eq!(seconds, BTreeMap::from([(0, 1), (2, 5)]));
+
+        let ms = sample_time_series(series.clone(), TimeResolution::Ms, 500, 1);
+        assert_eq!(ms, series);
+
+        let ms = sample_time_series(series, TimeResolution::Ms, 500, 4);
+        assert_eq!(ms, BTreeMap::from([(0, 0), (2000, 4)]));
+    }
+}
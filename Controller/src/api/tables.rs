@@ -76,7 +76,11 @@ pub async fn tables(State(state): State<Arc<AppState>>) -> Response {
         "ingress.p4tg.forward",
         "ingress.p4tg.frame_type.frame_type_monitor",
         "ingress.p4tg.frame_type.ethernet_type_monitor",
+        "ingress.p4tg.frame_type.custom_type_monitor",
+        "ingress.p4tg.flow.flow_monitor",
         "ingress.p4tg.tg_forward",
+        "ingress.p4tg.stream_index",
+        "ingress.p4tg.reorder_extent",
         "ingress.arp.arp_reply",
         "egress.frame_size_monitor",
         "egress.is_egress",
@@ -88,7 +92,7 @@ pub async fn tables(State(state): State<Arc<AppState>>) -> Response {
 
     // read all table entries
     let all_entries = {
-        let switch = &state.switch;
+        let switch = &*state.switch.read().await;
         let mut all_entries: Vec<(String, Vec<TableEntry>)> = vec![];
 
         for t in table_names {
@@ -28,10 +28,14 @@ use crate::api::multiple_traffic_gen::configure_multiple_traffic_gen;
 use std::collections::BTreeMap;
 
 use crate::api::server::Error;
+use crate::api::helper::validate::request_errors;
 use crate::AppState;
+use crate::error::P4TGError;
 
 use crate::api::docs::traffic_gen::{EXAMPLE_GET_1, EXAMPLE_GET_2, EXAMPLE_POST_1_REQUEST, EXAMPLE_POST_1_RESPONSE, EXAMPLE_POST_2_REQUEST, EXAMPLE_POST_3_REQUEST};
 use crate::core::traffic_gen_core::types::*;
+use crate::core::traffic_gen_core::planner::port_speeds;
+use crate::core::traffic_gen_core::helper::rx_port_groups;
 
 /// Method called on GET /trafficgen
 /// Returns the currently configured traffic generation(s) (see `all_test` field in `TrafficGenData`)
@@ -79,9 +83,12 @@ pub async fn traffic_gen(State(state): State<Arc<AppState>>) -> Response {
             stream_settings: tg.stream_settings.clone(),
             streams: tg.streams.clone(),
             port_tx_rx_mapping: tg.port_mapping.clone(),
+            port_tx_rx_groups: tg.port_groups.clone(),
             duration: None,
             name,
-            all_test: all_test_value
+            all_test: all_test_value,
+            analyze_filters: tg.analyze_filters.clone(),
+            flow_rules: tg.flow_rules.clone()
         };
 
         (StatusCode::OK, Json(tg_data)).into_response()
@@ -128,6 +135,55 @@ pub async fn configure_traffic_gen(State(state): State<Arc<AppState>>, payload:
     configure_multiple_traffic_gen(State(state), Json(vec![payload.0])).await
 }
 
+/// Method called on POST /trafficgen/plan
+/// Plans the traffic generation with the specified settings in the POST body without configuring the switch.
+/// Returns the calculated sending behaviour, the load of the generation and egress ports,
+/// the packet buffer layout, the multicast groups and the header rewrite entries.
+#[utoipa::path(
+    post,
+    path = "/api/trafficgen/plan",
+    request_body(
+        content = TrafficGenData,
+        examples(("Example 1" = (summary = "VxLAN 1024 (+50) byte @ 100 Gbps", value = json!(*EXAMPLE_POST_1_REQUEST))),
+                 ("Example 2" = (summary = "VLAN 64 (+4) byte @ 80 Gbps", value = json!(*EXAMPLE_POST_2_REQUEST)))
+        )
+    ),
+    responses(
+    (status = 200,
+    description = "Returns the planned traffic generation.",
+    body = TrafficGenPlan),
+    (status = 400,
+    description = "Returned when the traffic generation is invalid. The `errors` field contains all validation errors.",
+    body = TrafficGenPlan)
+    )
+)]
+pub async fn plan_traffic_gen(State(state): State<Arc<AppState>>, payload: Json<TrafficGenData>) -> Response {
+    let tg = &state.traffic_generator.lock().await;
+
+    // only look at active stream settings
+    let active_stream_settings: Vec<StreamSetting> = payload.stream_settings.clone().into_iter().filter(|s| s.active).collect();
+    let active_stream_ids: Vec<u8> = active_stream_settings.iter().map(|s| s.stream_id).collect();
+    let active_streams: Vec<Stream> = payload.streams.clone().into_iter().filter(|s| active_stream_ids.contains(&s.stream_id)).collect();
+
+    let port_speeds = match port_speeds(&state.pm, &*state.switch.read().await).await {
+        Ok(speeds) => speeds,
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(Error::new(format!("{:#?}", err)))).into_response()
+    };
+
+    // contains the mapping of Send->Receive ports
+    let port_mapping = rx_port_groups(&payload.port_tx_rx_mapping, payload.port_tx_rx_groups.as_ref());
+
+    let errors = request_errors(&payload, &active_streams, &active_stream_settings, &port_mapping, &state.port_mapping, tg.is_tofino2, &port_speeds);
+
+    if !errors.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(TrafficGenPlan { streams: active_streams, errors, ..Default::default() })).into_response();
+    }
+
+    match tg.plan_traffic_generation(&state.port_mapping, &port_speeds, active_streams.clone(), payload.mode, &active_stream_settings) {
+        Ok(plan) => (StatusCode::OK, Json(plan)).into_response(),
+        Err(P4TGError::Error { message }) => (StatusCode::BAD_REQUEST, Json(TrafficGenPlan { streams: active_streams, errors: vec![message], ..Default::default() })).into_response()
+    }
+}
 
 #[utoipa::path(
     delete,
@@ -139,7 +195,7 @@ pub async fn configure_traffic_gen(State(state): State<Arc<AppState>>, payload:
 /// Stops the current traffic generation
 pub async fn stop_traffic_gen(State(state): State<Arc<AppState>>) -> Response {
     let tg = &state.traffic_generator;
-    let switch = &state.switch;
+    let switch = &*state.switch.read().await;
 
     match tg.lock().await.stop(switch).await {
         Ok(_) => {
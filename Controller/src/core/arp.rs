@@ -18,6 +18,7 @@
  */
 
 use std::collections::HashMap;
+use std::sync::Mutex;
 use log::info;
 
 use rbfrt::error::RBFRTError;
@@ -31,11 +32,15 @@ const ACTION_PREFIX: &str = "ingress.arp";
 
 /// This module handles the initialization of the `ingress.arp.arp_reply` table
 /// that decides if arp requests are answered
-pub struct Arp;
+pub struct Arp {
+    /// ARP reply state per RX recirculation port as set via [Arp::modify_arp].
+    /// The state is restored with [Arp::restore], e.g., after a reconnect to the switch.
+    active: Mutex<HashMap<u32, bool>>
+}
 
 impl Arp {
     pub fn new() -> Arp {
-        Arp { }
+        Arp { active: Mutex::new(HashMap::new()) }
     }
 
     pub async fn init(&self, switch: &SwitchConnection, port_mapping: &HashMap<u32, PortMapping>) -> Result<(), RBFRTError> {
@@ -71,10 +76,26 @@ impl Arp {
 
         switch.update_table_entry(req).await?;
 
+        self.active.lock().unwrap().insert(port.rx_recirculation, active);
+
         info!("ARP reply rule for rx port {} change to {}.", port.rx_recirculation, active);
 
         Ok(())
     }
+
+    /// Restores the ARP reply state of the ports after [Arp::init], e.g., after a reconnect to the switch.
+    pub async fn restore(&self, switch: &SwitchConnection, port_mapping: &HashMap<u32, PortMapping>) -> Result<(), RBFRTError> {
+        let active: Vec<PortMapping> = {
+            let state = self.active.lock().unwrap();
+            port_mapping.values().filter(|mapping| state.get(&mapping.rx_recirculation).copied().unwrap_or(false)).copied().collect()
+        };
+
+        for mapping in &active {
+            self.modify_arp(switch, mapping, true).await?;
+        }
+
+        Ok(())
+    }
 }
 
 
@@ -1,18 +1,123 @@
+use std::fs::File;
 use std::str::FromStr;
+use log::warn;
 use macaddr::MacAddr;
-use serde::{Deserialize, Serialize};
+use rbfrt::util::port_manager::{AutoNegotiation, FEC, Speed};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::{Error, IntoDeserializer};
+use crate::core::{FrameSizeBin, FrameSizeMonitor};
+use crate::core::traffic_gen_core::const_definitions::MAX_TG_PORTS;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PortDescription {
     pub(crate) port: u32,
     pub(crate) mac: String,
-    arp_reply: Option<bool>
+    pub(crate) arp_reply: Option<bool>,
+    /// Speed of the port. Defaults to 100G on Tofino1 and 400G on Tofino2.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "deserialize_speed")]
+    pub(crate) speed: Option<Speed>,
+    /// FEC of the port. Defaults to no FEC on Tofino1 and Reed-Solomon on Tofino2.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) fec: Option<FEC>,
+    /// Auto negotiation of the port. Defaults to PM_AN_DEFAULT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) auto_neg: Option<AutoNegotiation>,
+    /// Breakout channels of the port, e.g., [0, 1, 2, 3] for 4x25G.
+    /// Each channel is used as an individual traffic generation port. Defaults to channel 0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) channels: Option<Vec<u8>>
 }
 
+impl PortDescription {
+    /// Returns the configured breakout channels of the port
+    pub(crate) fn channels(&self) -> Vec<u8> {
+        self.channels.clone().unwrap_or(vec![0])
+    }
+
+    /// Validates the breakout channels against the speed of the port.
+    ///
+    /// A port has 4 (Tofino 1) or 8 (Tofino 2) serdes lanes.
+    /// A channel starts at the lane of its channel number and occupies the lanes that its speed requires,
+    /// e.g., 100G requires all 4 lanes of a Tofino 1 port and therefore only allows channel 0.
+    pub(crate) fn validate_breakout(&self, is_tofino2: bool) -> Result<(), String> {
+        let (asic, n_lanes) = if is_tofino2 { ("Tofino 2", 8) } else { ("Tofino 1", 4) };
+        let speed = self.speed.clone().unwrap_or(if is_tofino2 { Speed::BF_SPEED_400G } else { Speed::BF_SPEED_100G });
+
+        let lanes = match (&speed, is_tofino2) {
+            (Speed::BF_SPEED_400G, true) => 8,
+            (Speed::BF_SPEED_400G, false) => return Err(format!("Speed {:?} of port {} is not supported on {}.", speed, self.port, asic)),
+            (Speed::BF_SPEED_100G | Speed::BF_SPEED_40G, false) => 4,
+            (Speed::BF_SPEED_100G | Speed::BF_SPEED_40G, true) | (Speed::BF_SPEED_50G, false) => 2,
+            (Speed::BF_SPEED_50G, true) => 1,
+            (Speed::BF_SPEED_1G | Speed::BF_SPEED_10G | Speed::BF_SPEED_20G | Speed::BF_SPEED_25G, _) => 1
+        };
+
+        if let Some(channel) = self.channels().into_iter().find(|c| c % lanes != 0 || c + lanes > n_lanes) {
+            let valid: Vec<u8> = (0..n_lanes).step_by(lanes as usize).collect();
+
+            return Err(format!("Channel {} of port {} is not available with speed {:?} on {}. Available channels: {:?}.", channel, self.port, speed, asic, valid));
+        }
+
+        Ok(())
+    }
+}
+
+/// Deserializes the speed of a port.
+/// 200G, e.g., for a 2x200G breakout on Tofino 2, is rejected explicitly as the port manager does not support it.
+fn deserialize_speed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Speed>, D::Error> {
+    match Option::<String>::deserialize(deserializer)?.as_deref() {
+        None => Ok(None),
+        Some("BF_SPEED_200G") => Err(D::Error::custom("Speed BF_SPEED_200G is not supported. Use BF_SPEED_400G or a breakout with BF_SPEED_100G instead.")),
+        Some(speed) => Speed::deserialize(speed.into_deserializer()).map(Some)
+    }
+}
+
+
+/// Connection to the BF Runtime gRPC server of a device
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Connection {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) device_id: u32,
+    pub(crate) client_id: u32
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Connection {
+            host: "localhost".to_owned(),
+            port: 50052,
+            device_id: 0,
+            client_id: 1
+        }
+    }
+}
+
+/// Content of the config file.
+/// Either the config of a single device, or a list of device configs.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConfigFile {
+    Devices { devices: Vec<Config> },
+    Single(Config)
+}
 
 #[derive(Deserialize, Debug, Serialize, Clone)]
 pub struct Config {
-    pub(crate) tg_ports: Vec<PortDescription>
+    /// Name of the device. Defaults to the host of the connection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    /// Connection to the BF Runtime server of the device. Defaults to localhost:50052, device id 0 and client id 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) connection: Option<Connection>,
+    pub(crate) tg_ports: Vec<PortDescription>,
+    /// Front panel ports that are used for recirculation. Two recirculation ports are required per traffic generation port.
+    /// Defaults to all front panel ports of the device that are not used for traffic generation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) recirculation_ports: Option<Vec<u32>>,
+    /// Frame size bins of the frame size statistics. Defaults to the RFC 2819 bins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) frame_size_bins: Option<Vec<FrameSizeBin>>
 }
 
 impl Default for Config {
@@ -21,16 +126,62 @@ impl Default for Config {
                         "d6:67:75:a1:94:c3", "e2:bd:1e:02:dc:b4", "d0:b3:7f:59:2c:4a",  "84:08:f3:bc:2b:ac",
                         "06:6c:cc:db:86:9c", "c0:db:54:17:15:0f"];
         Config {
+            name: None,
+            connection: None,
             tg_ports: (1..11).collect::<Vec<_>>()
                 .iter()
                 .enumerate()
-                .map(|(i, v)| PortDescription { port: *v, mac: macs.get(i).unwrap().parse().unwrap(), arp_reply: None })
-                .collect()
+                .map(|(i, v)| PortDescription { port: *v, mac: macs.get(i).unwrap().parse().unwrap(), arp_reply: None, speed: None, fec: None, auto_neg: None, channels: None })
+                .collect(),
+            recirculation_ports: None,
+            frame_size_bins: None
         }
     }
 }
 
 impl Config {
+    /// Loads the device configs from the config file at `path`.
+    /// Falls back to the default config if the file does not exist or is not valid.
+    /// Invalid device configs keep their name and connection, but use the default ports.
+    pub fn load(path: &str) -> Vec<Config> {
+        let configs = match File::open(path) {
+            Ok(file) => {
+                match serde_json::from_reader(file) {
+                    Ok(ConfigFile::Devices { devices }) => devices,
+                    Ok(ConfigFile::Single(config)) => vec![config],
+                    Err(err) => {
+                        warn!("Config file not valid ({}). Using default config.", err);
+                        vec![Config::default()]
+                    }
+                }
+            }
+            Err(_) => {
+                warn!("No config file ({}) for controller found. Using default config.", path);
+                vec![Config::default()]
+            }
+        };
+
+        configs.into_iter().map(|config| {
+            match config.validate() {
+                Ok(_) => config,
+                Err(err) => {
+                    warn!("Config of device {} not valid. {} Using default config.", config.name(), err);
+                    Config { name: config.name, connection: config.connection, ..Config::default() }
+                }
+            }
+        }).collect()
+    }
+
+    /// Returns the connection to the BF Runtime server of the device
+    pub(crate) fn connection(&self) -> Connection {
+        self.connection.clone().unwrap_or_default()
+    }
+
+    /// Returns the name of the device
+    pub(crate) fn name(&self) -> String {
+        self.name.clone().unwrap_or(self.connection().host)
+    }
+
     pub fn contains(&self, other: u32) -> bool {
         for i in &self.tg_ports {
             if i.port == other {
@@ -41,15 +192,75 @@ impl Config {
         false
     }
 
-    pub(crate) fn validate(&self) -> bool {
+    /// Validates the config and returns a description of the first problem.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let mut tg_ports = vec![];
+
         for port in &self.tg_ports {
             if MacAddr::from_str(&port.mac).is_err() {
-                return false;
+                return Err(format!("MAC address {} of port {} is not valid.", port.mac, port.port));
+            }
+
+            // channels of a port have to be unique and at most 8 channels exist
+            let mut channels = port.channels();
+            channels.sort();
+            channels.dedup();
+
+            if channels.is_empty() || channels.len() != port.channels().len() || channels.iter().any(|c| *c > 7) {
+                return Err(format!("Channels of port {} have to be unique and between 0 and 7.", port.port));
+            }
+
+            // the ASIC is not known before the connection to the device
+            // the breakout has to be valid on at least one of them and is checked again when the ports are configured
+            port.validate_breakout(false).or_else(|err| port.validate_breakout(true).map_err(|_| err))?;
+
+            if tg_ports.contains(&port.port) {
+                return Err(format!("Port {} is configured multiple times.", port.port));
+            }
+
+            tg_ports.push(port.port);
+        }
+
+        // each channel is an individual traffic generation port
+        let n_tg_ports: usize = self.tg_ports.iter().map(|p| p.channels().len()).sum();
+
+        if n_tg_ports > MAX_TG_PORTS {
+            return Err(format!("{} traffic generation ports configured, but the data plane supports at most {}.", n_tg_ports, MAX_TG_PORTS));
+        }
+
+        if let Some(bins) = &self.frame_size_bins {
+            FrameSizeMonitor::validate_bins(bins, n_tg_ports)?;
+        }
+
+        if let Some(recirculation_ports) = &self.recirculation_ports {
+            let mut unique = recirculation_ports.clone();
+            unique.sort();
+            unique.dedup();
+
+            if unique.len() != recirculation_ports.len() {
+                return Err("Recirculation ports have to be unique.".to_owned());
+            }
+
+            if let Some(port) = recirculation_ports.iter().find(|p| self.contains(**p)) {
+                return Err(format!("Port {} is used for traffic generation and recirculation.", port));
             }
         }
 
-        self.tg_ports.len() <= 10
-            && self.tg_ports.clone().into_iter().filter(|p| p.port > 32).collect::<Vec<_>>().is_empty()
+        Ok(())
+    }
+
+    /// Returns the front panel ports that are used for recirculation.
+    /// These are either the configured recirculation ports,
+    /// or all available front panel ports that are not used for traffic generation.
+    ///
+    /// # Arguments
+    ///
+    /// * `front_panel_ports`: Front panel ports of the device
+    pub(crate) fn recirculation_ports(&self, front_panel_ports: &[u32]) -> Vec<u32> {
+        match &self.recirculation_ports {
+            Some(ports) => ports.clone(),
+            None => front_panel_ports.iter().filter(|p| !self.contains(**p)).copied().collect()
+        }
     }
 
     pub(crate) fn update_arp_state(&mut self, port: u32, state: bool) {
@@ -59,4 +270,57 @@ impl Config {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(speed: Option<Speed>, channels: Vec<u8>) -> PortDescription {
+        PortDescription { port: 1, mac: "fa:a6:68:e0:3d:70".to_owned(), arp_reply: None, speed, fec: None, auto_neg: None, channels: Some(channels) }
+    }
+
+    #[test]
+    fn breakout_matches_speed() {
+        assert!(port(Some(Speed::BF_SPEED_25G), vec![0, 1, 2, 3]).validate_breakout(false).is_ok());
+        assert!(port(Some(Speed::BF_SPEED_50G), vec![0, 2]).validate_breakout(false).is_ok());
+        assert!(port(Some(Speed::BF_SPEED_100G), vec![0, 2, 4, 6]).validate_breakout(true).is_ok());
+        assert!(port(None, vec![0]).validate_breakout(true).is_ok());
+    }
+
+    #[test]
+    fn breakout_exceeds_port() {
+        assert!(port(Some(Speed::BF_SPEED_100G), vec![0, 1, 2, 3]).validate_breakout(false).is_err());
+        assert!(port(Some(Speed::BF_SPEED_100G), vec![0, 1, 2, 3]).validate_breakout(true).is_err());
+        assert!(port(Some(Speed::BF_SPEED_50G), vec![0, 1]).validate_breakout(false).is_err());
+        assert!(port(Some(Speed::BF_SPEED_25G), vec![4]).validate_breakout(false).is_err());
+        assert!(port(Some(Speed::BF_SPEED_400G), vec![0]).validate_breakout(false).is_err());
+        assert!(port(None, vec![0, 4]).validate_breakout(true).is_err());
+    }
+
+    #[test]
+    fn single_lane_speeds() {
+        for speed in [Speed::BF_SPEED_1G, Speed::BF_SPEED_10G, Speed::BF_SPEED_20G, Speed::BF_SPEED_25G] {
+            assert!(port(Some(speed.clone()), vec![0, 1, 2, 3]).validate_breakout(false).is_ok());
+            assert!(port(Some(speed.clone()), vec![4]).validate_breakout(false).is_err());
+            assert!(port(Some(speed), (0..8).collect()).validate_breakout(true).is_ok());
+        }
+    }
+
+    #[test]
+    fn single_lane_50g_on_tofino2() {
+        assert!(port(Some(Speed::BF_SPEED_50G), (0..8).collect()).validate_breakout(true).is_ok());
+        assert!(port(Some(Speed::BF_SPEED_50G), vec![1]).validate_breakout(false).is_err());
+    }
+
+    #[test]
+    fn speed_200g_is_rejected() {
+        let description = |speed: &str| format!(r#"{{"port": 1, "mac": "fa:a6:68:e0:3d:70", "arp_reply": null, "speed": "{}", "channels": [0, 4]}}"#, speed);
+
+        let err = serde_json::from_str::<PortDescription>(&description("BF_SPEED_200G")).unwrap_err();
+        assert!(err.to_string().contains("BF_SPEED_200G is not supported"));
+
+        assert!(serde_json::from_str::<PortDescription>(&description("BF_SPEED_100G")).is_ok());
+        assert!(serde_json::from_str::<PortDescription>(&description("BF_SPEED_300G")).is_err());
+        assert!(serde_json::from_str::<PortDescription>(r#"{"port": 1, "mac": "fa:a6:68:e0:3d:70", "arp_reply": null}"#).unwrap().speed.is_none());
+    }
+}
@@ -0,0 +1,268 @@
+/* Copyright 2022-present University of Tuebingen, Chair of Communication Networks
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
+ */
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use rbfrt::error::RBFRTError;
+use rbfrt::{SwitchConnection, table};
+use rbfrt::table::{MatchValue, ToBytes};
+use crate::{AppState, PortMapping};
+
+use log::{info, warn};
+use crate::core::traffic_gen_core::types::{FlowRule, GenerationMode};
+use crate::core::statistics::{FlowCounter, FlowStatistics};
+use crate::core::traffic_gen_core::event::TrafficGenEvent;
+
+/// Table that classifies received frames into the user-defined flows.
+pub const FLOW_MONITOR: &str = "ingress.p4tg.flow.flow_monitor";
+
+/// Maximal number of entries of the [FLOW_MONITOR] table.
+pub const FLOW_MONITOR_SIZE: usize = 1024;
+
+/// This struct handles the [FLOW_MONITOR] table that counts packets and bytes
+/// of user-defined flows in the [GenerationMode::Analyze] mode.
+/// Each flow rule is installed on the RX recirculation port of its RX port(s).
+/// The index of the rule in `rules` is used as the flow id and determines the match priority.
+pub struct FlowMonitor {
+    /// Stores the mapping between front panel port and tx/rx recirculation ports
+    port_mapping: HashMap<u32, PortMapping>,
+    /// Flow classification rules of the current traffic generation
+    rules: Vec<FlowRule>,
+    /// RX ports on which rules without a port are installed
+    rx_ports: Vec<u32>,
+    /// Rules are only installed in the [GenerationMode::Analyze] mode
+    active: bool,
+    pub statistics: FlowStatistics
+}
+
+impl FlowMonitor {
+    pub fn new(port_mapping: HashMap<u32, PortMapping>) -> FlowMonitor {
+        FlowMonitor { port_mapping, rules: vec![], rx_ports: vec![], active: false, statistics: FlowStatistics::default() }
+    }
+
+    /// Sets the flow rules and the RX ports of the `tx_rx_mapping` they are applied on.
+    /// The rules are written to the switch on the next [TrafficGenEvent::on_start] or [TrafficGenEvent::on_reset].
+    pub fn set_rules(&mut self, rules: Vec<FlowRule>, tx_rx_mapping: &HashMap<u32, Vec<u32>>) {
+        let mut rx_ports: Vec<u32> = tx_rx_mapping.values().flatten().copied().collect();
+        rx_ports.sort();
+        rx_ports.dedup();
+
+        self.rules = rules;
+        self.rx_ports = rx_ports;
+    }
+
+    /// Configures the [flow monitor table](FLOW_MONITOR) in the ingress pipeline.
+    /// It first clears the table, then rewrites it if the flow monitor is active.
+    pub async fn configure(&self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
+        self.clear(switch).await?;
+
+        if !self.active || self.rules.is_empty() {
+            return Ok(());
+        }
+
+        info!("Configure table {}.", FLOW_MONITOR);
+
+        let mut table_entries = vec![];
+
+        for (flow_id, rule) in self.rules.iter().enumerate() {
+            let ports = match rule.port {
+                Some(port) => vec![port],
+                None => self.rx_ports.clone()
+            };
+
+            let (vlan_valid, vlan_id) = match rule.vlan_id {
+                Some(id) => (MatchValue::ternary(1u8, 1u8), MatchValue::ternary(id, 0xFFF)),
+                None => (MatchValue::ternary(0u8, 0u8), MatchValue::ternary(0u16, 0u16))
+            };
+
+            let ip_required = rule.ip_src.is_some() || rule.ip_dst.is_some() || rule.protocol.is_some();
+
+            let prefix = |ip: Option<Ipv4Addr>, length: Option<u8>| match ip {
+                Some(ip) => {
+                    let mask = u32::MAX.checked_shl(32 - length.unwrap_or(32).min(32) as u32).unwrap_or(0);
+                    MatchValue::ternary(u32::from(ip) & mask, mask)
+                }
+                None => MatchValue::ternary(0u32, 0u32)
+            };
+
+            let l4_port = |port: Option<u16>| match port {
+                Some(port) => MatchValue::ternary(port, 0xFFFF),
+                None => MatchValue::ternary(0u16, 0u16)
+            };
+
+            for port in ports {
+                let Some(mapping) = self.port_mapping.get(&port) else {
+                    warn!("Flow rule {} references unknown port {}.", rule.name, port);
+                    continue;
+                };
+
+                // lower value, higher priority
+                table_entries.push(table::Request::new(FLOW_MONITOR)
+                    .match_key("ig_intr_md.ingress_port", MatchValue::exact(mapping.rx_recirculation))
+                    .match_key("hdr.vlan.$valid", vlan_valid.clone())
+                    .match_key("hdr.vlan.vid", vlan_id.clone())
+                    .match_key("hdr.inner_ipv4.$valid", if ip_required {MatchValue::ternary(1u8, 1u8)} else {MatchValue::ternary(0u8, 0u8)})
+                    .match_key("hdr.inner_ipv4.src_addr", prefix(rule.ip_src, rule.ip_src_prefix))
+                    .match_key("hdr.inner_ipv4.dst_addr", prefix(rule.ip_dst, rule.ip_dst_prefix))
+                    .match_key("hdr.inner_ipv4.protocol", match rule.protocol {
+                        Some(protocol) => MatchValue::ternary(protocol, 0xFFu8),
+                        None => MatchValue::ternary(0u8, 0u8)
+                    })
+                    .match_key("ig_md.l4_src_port", l4_port(rule.src_port))
+                    .match_key("ig_md.l4_dst_port", l4_port(rule.dst_port))
+                    .match_key("$MATCH_PRIORITY", MatchValue::exact(flow_id as u32 + 1))
+                    .action("ingress.p4tg.flow.flow")
+                    .action_data("flow_id", flow_id as u16));
+            }
+        }
+
+        // dispatch all at once
+        switch.write_table_entries(table_entries).await?;
+
+        Ok(())
+    }
+
+    /// Computes the flow statistics for the configuration GUI.
+    /// Rates are calculated from the counter difference between two polls.
+    ///
+    /// - `state`: App state that holds the switch connection.
+    pub async fn monitor_statistics(state: Arc<AppState>) {
+        // (rx port, flow id) -> (packets, bytes)
+        let mut last_counts: HashMap<(u32, u16), (u128, u128)> = HashMap::new();
+        let mut last_poll = Instant::now();
+
+        loop {
+            let mut stats = FlowStatistics::default();
+
+            let request = table::Request::new(FLOW_MONITOR);
+            let sync = table::Request::new(FLOW_MONITOR).operation(table::TableOperation::SyncCounters);
+
+            let entries = {
+                let switch = &*state.switch.read().await;
+
+                // sync counters
+                if switch.execute_operation(sync).await.is_err() {
+                    warn! {"Encountered error while synchronizing {}.", FLOW_MONITOR};
+                }
+
+                // read counters
+                switch.get_table_entry(request).await.unwrap_or_else(|err| {
+                    warn! {"Encountered error while retrieving {} table. Error: {:#?}", FLOW_MONITOR, err};
+                    vec![]
+                })
+            };
+
+            let elapsed = last_poll.elapsed().as_secs_f64();
+            last_poll = Instant::now();
+
+            let rx_mapping: HashMap<u32, u32> = state.port_mapping.iter().map(|(port, mapping)| (mapping.rx_recirculation, *port)).collect();
+
+            let names: Vec<String> = state.flow_monitor.lock().await.rules.iter().map(|r| r.name.clone()).collect();
+
+            let mut counts: HashMap<(u32, u16), (u128, u128)> = HashMap::new();
+
+            for entry in entries {
+                if !entry.match_key.contains_key("ig_intr_md.ingress_port") { // filter out default entry
+                    continue;
+                }
+
+                let recirc_port = entry.match_key.get("ig_intr_md.ingress_port").unwrap().get_exact_value().to_u32();
+
+                let Some(port) = rx_mapping.get(&recirc_port) else {
+                    continue;
+                };
+
+                let mut flow_id = None;
+                let mut packets = 0;
+                let mut bytes = 0;
+
+                for action in &entry.action_data {
+                    match action.get_name() {
+                        "flow_id" => flow_id = Some(action.get_data().to_u32() as u16),
+                        "$COUNTER_SPEC_PKTS" => packets = action.get_data().to_u128(),
+                        "$COUNTER_SPEC_BYTES" => bytes = action.get_data().to_u128(),
+                        _ => {}
+                    }
+                }
+
+                let Some(flow_id) = flow_id else {
+                    continue;
+                };
+
+                let Some(name) = names.get(flow_id as usize) else {
+                    continue;
+                };
+
+                let (last_packets, last_bytes) = last_counts.get(&(*port, flow_id)).copied().unwrap_or((packets, bytes));
+
+                let (packet_rate, rate_l2) = if elapsed > 0f64 {
+                    (packets.saturating_sub(last_packets) as f64 / elapsed, 8f64 * bytes.saturating_sub(last_bytes) as f64 / elapsed)
+                } else {
+                    (0f64, 0f64)
+                };
+
+                counts.insert((*port, flow_id), (packets, bytes));
+
+                stats.flow_rx.entry(*port).or_default().insert(name.clone(), FlowCounter {
+                    packets,
+                    bytes,
+                    packet_rate,
+                    rate_l2
+                });
+            }
+
+            last_counts = counts;
+
+            {
+                let flow_state = &mut state.flow_monitor.lock().await;
+                flow_state.statistics = stats;
+            }
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Clear the [flow monitor table](FLOW_MONITOR).
+    pub async fn clear(&self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
+        switch.clear_table(FLOW_MONITOR).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TrafficGenEvent for FlowMonitor {
+    async fn on_start(&mut self, switch: &SwitchConnection, mode: &GenerationMode) -> Result<(), RBFRTError> {
+        self.active = *mode == GenerationMode::Analyze;
+        self.configure(switch).await?;
+        Ok(())
+    }
+
+    async fn on_stop(&self, _switch: &SwitchConnection) -> Result<(), RBFRTError> {
+        Ok(())
+    }
+
+    async fn on_reset(&mut self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
+        self.configure(switch).await?;
+        Ok(())
+    }
+}
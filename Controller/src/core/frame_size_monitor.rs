@@ -21,6 +21,8 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use rbfrt::error::RBFRTError;
 use rbfrt::{SwitchConnection, table};
 use rbfrt::table::{MatchValue, ToBytes};
@@ -36,6 +38,23 @@ use crate::core::traffic_gen_core::event::TrafficGenEvent;
 /// The table matches on the packet size in a range match.
 const FRAME_SIZE_MONITOR: &str = "egress.frame_size_monitor";
 
+/// Maximal number of entries of the [FRAME_SIZE_MONITOR] table.
+const FRAME_SIZE_MONITOR_SIZE: usize = 512;
+
+/// Largest frame size that can be matched, the packet length is a 16 bit field.
+const MAX_FRAME_SIZE: u32 = u16::MAX as u32;
+
+/// Frame size bin that counts frames with low <= frame size <= high.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FrameSizeBin {
+    /// Lower bound of the frame size
+    #[schema(example = 64)]
+    pub(crate) low: u32,
+    /// Upper bound of the frame size
+    #[schema(example = 127)]
+    pub(crate) high: u32
+}
+
 /// This struct handles the initialization of the [FRAME_SIZE_MONITOR] table
 /// that counts the different frame sizes that are received/sent.
 /// The P4 table uses a counter that counts the matched packets on the table entries.
@@ -44,16 +63,62 @@ pub struct FrameSizeMonitor {
     /// Stores the mapping between front panel port and tx/rx recirculation ports
     port_mapping: HashMap<u32, PortMapping>,
     /// Describes the entries of the [FRAME_SIZE_MONITOR] table.
-    /// Format (a, b) results in the range entry from a until a+b.
-    frame_ranges: Vec<(u32, u32)>,
+    /// Each bin results in one range entry per TX and RX path of a port.
+    frame_ranges: Vec<FrameSizeBin>,
     pub statistics: FrameSizeStatistics,
 }
 
 impl FrameSizeMonitor {
-    pub fn new(port_mapping: HashMap<u32, PortMapping>) -> FrameSizeMonitor {
+    /// Creates the frame size monitor with the given `frame_ranges`.
+    /// Defaults to the RFC 2819 frame size bins.
+    pub fn new(port_mapping: HashMap<u32, PortMapping>, frame_ranges: Option<Vec<FrameSizeBin>>) -> FrameSizeMonitor {
+        let frame_ranges = frame_ranges.unwrap_or_else(FrameSizeMonitor::default_bins);
+        FrameSizeMonitor {port_mapping, frame_ranges, statistics: FrameSizeStatistics::default()}
+    }
+
+    /// Frame size bins according to RFC 2819
+    pub fn default_bins() -> Vec<FrameSizeBin> {
         // entry (a, b) describes range (a, a+b)
         let frame_ranges = vec![(0, 63), (64, 0), (65, 62), (128, 127), (256, 255), (512, 511), (1024, 494), (1519, 20000)];
-        FrameSizeMonitor {port_mapping, frame_ranges, statistics: FrameSizeStatistics::default()}
+        frame_ranges.into_iter().map(|(low, width)| FrameSizeBin { low, high: low + width }).collect()
+    }
+
+    /// Returns the configured frame size bins.
+    pub fn bins(&self) -> Vec<FrameSizeBin> {
+        self.frame_ranges.clone()
+    }
+
+    /// Sets the frame size bins. The bins are written to the switch on the next [FrameSizeMonitor::configure].
+    pub fn set_bins(&mut self, frame_ranges: Vec<FrameSizeBin>) {
+        self.frame_ranges = frame_ranges;
+    }
+
+    /// Validates that the frame size `bins` do not overlap and that the entries for
+    /// `n_ports` traffic generation ports fit into the [FRAME_SIZE_MONITOR] table.
+    pub fn validate_bins(bins: &[FrameSizeBin], n_ports: usize) -> Result<(), String> {
+        if bins.is_empty() {
+            return Err("At least one frame size bin is required.".to_owned());
+        }
+
+        if let Some(bin) = bins.iter().find(|b| b.low > b.high || b.high > MAX_FRAME_SIZE) {
+            return Err(format!("Frame size bin ({}, {}) is not valid. Bounds have to be ascending and at most {}.", bin.low, bin.high, MAX_FRAME_SIZE));
+        }
+
+        let mut sorted = bins.to_vec();
+        sorted.sort_by_key(|b| b.low);
+
+        if let Some(w) = sorted.windows(2).find(|w| w[1].low <= w[0].high) {
+            return Err(format!("Frame size bins ({}, {}) and ({}, {}) overlap.", w[0].low, w[0].high, w[1].low, w[1].high));
+        }
+
+        // one entry for the TX and one entry for the RX path per port
+        let entries = 2 * n_ports * bins.len();
+
+        if entries > FRAME_SIZE_MONITOR_SIZE {
+            return Err(format!("{} frame size bins require {} table entries for {} ports, but the data plane supports at most {}.", bins.len(), entries, n_ports, FRAME_SIZE_MONITOR_SIZE));
+        }
+
+        Ok(())
     }
 
     /// Configures the [frame size monitor table](FRAME_SIZE_MONITOR) in the egress pipeline.
@@ -69,19 +134,19 @@ impl FrameSizeMonitor {
         // build table requests
         // we used batched execution
         for (port, mapping) in self.port_mapping.iter().by_ref() {
-            for (lower, upper) in &self.frame_ranges {
+            for bin in &self.frame_ranges {
 
                 // table entry for the TX path
                 let tx_add_request = table::Request::new(FRAME_SIZE_MONITOR)
                     .match_key("eg_intr_md.egress_port", MatchValue::exact(*port))
-                    .match_key("pkt_len", MatchValue::range(*lower, *lower + *upper))
+                    .match_key("pkt_len", MatchValue::range(bin.low, bin.high))
                     .match_key("$MATCH_PRIORITY", MatchValue::exact(1))
                     .action("egress.nop");
 
                 // table entry for the RX path
                 let rx_add_request = table::Request::new(FRAME_SIZE_MONITOR)
                     .match_key("eg_intr_md.egress_port", MatchValue::exact(mapping.rx_recirculation))
-                    .match_key("pkt_len", MatchValue::range(*lower, *lower + *upper))
+                    .match_key("pkt_len", MatchValue::range(bin.low, bin.high))
                     .match_key("$MATCH_PRIORITY", MatchValue::exact(1))
                     .action("egress.nop");
 
@@ -107,7 +172,7 @@ impl FrameSizeMonitor {
             let sync = table::Request::new(FRAME_SIZE_MONITOR).operation(table::TableOperation::SyncCounters);
 
             let entries = {
-                let switch = &state.switch;
+                let switch = &*state.switch.read().await;
 
                 // sync counters
                 if switch.execute_operation(sync).await.is_err() {
@@ -17,11 +17,13 @@
  * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
  */
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::Ipv4Addr;
 use std::sync::Arc;
 use std::time::Duration;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use rbfrt::error::RBFRTError;
 use rbfrt::{SwitchConnection, table};
 use rbfrt::table::{MatchValue, ToBytes};
@@ -35,16 +37,58 @@ use crate::core::traffic_gen_core::event::TrafficGenEvent;
 const FRAME_TYPE_MONITOR: &str = "ingress.p4tg.frame_type.frame_type_monitor";
 const ETHERNET_TYPE_MONITOR: &str = "ingress.p4tg.frame_type.ethernet_type_monitor";
 
+/// Table that counts the user-defined frame types.
+const CUSTOM_TYPE_MONITOR: &str = "ingress.p4tg.frame_type.custom_type_monitor";
+
+/// Maximal number of entries of the [CUSTOM_TYPE_MONITOR] table.
+const CUSTOM_TYPE_MONITOR_SIZE: usize = 512;
+
+/// Names of the built-in frame types that can not be used for user-defined frame types.
+const BUILT_IN_TYPES: [&str; 13] = ["multicast", "unicast", "broadcast", "vxlan", "ipv4", "ipv6", "vlan", "q_in_q", "qinq", "arp", "mpls", "unknown", "custom"];
+
 const ACTION_PREFIX: &str = "ingress.p4tg.frame_type";
 
-/// This module handles the initialization of the `egress.frame_size_monitor` table
-/// that counts the different frame sizes that are received/sent
+/// User-defined frame type that is counted as a named counter in the frame type statistics.
+/// A frame is counted if it matches all set fields.
+/// If a frame matches several user-defined frame types, it is counted for the type that was added first.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FrameTypeClassifier {
+    /// Name of the counter, e.g., "lldp"
+    #[schema(example = "lldp")]
+    pub(crate) name: String,
+    /// EtherType of the frame, e.g., 0x88CC (35020) for LLDP
+    #[schema(example = 35020)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) ether_type: Option<u16>,
+    /// IPv4 source address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub(crate) ip_src: Option<Ipv4Addr>,
+    /// Prefix length of the IPv4 source address. Defaults to 32.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) ip_src_prefix: Option<u8>,
+    /// IPv4 destination address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub(crate) ip_dst: Option<Ipv4Addr>,
+    /// Prefix length of the IPv4 destination address. Defaults to 32.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) ip_dst_prefix: Option<u8>
+}
+
+/// This module handles the initialization of the frame type monitor tables
+/// that count the different frame types that are received/sent
 pub struct FrameTypeMonitor {
     port_mapping: HashMap<u32, PortMapping>,
     /// (IP adress, LPM, VxLAN flag, action)
     ip_lpm_entries: Vec<([u8; 4], u32, u8, String)>,
     /// (Ethertype, Action)
     ethernet_types: Vec<(u16, String)>,
+    /// User-defined frame types by class id.
+    /// The class id is written as action data and determines the match priority.
+    custom_types: BTreeMap<u16, FrameTypeClassifier>,
+    /// Class id of the next user-defined frame type
+    next_class_id: u16,
     pub statistics: FrameTypeStatistics
 }
 
@@ -53,7 +97,108 @@ impl FrameTypeMonitor {
         // (IP adress, LPM)
         let ip_lpm_entries = vec![([224, 0, 0, 0], 8, 0, "multicast".to_owned()), ([0, 0, 0, 0], 0, 0, "unicast".to_owned()), ([0, 0, 0, 0], 0, 1, "vxlan".to_owned())];
         let ethernet_types = vec![(0x800, "ipv4".to_owned()), (0x86DD, "ipv6".to_owned()), (0x8100, "vlan".to_owned()), (0x88a8, "q_in_q".to_owned()), (0x0806, "arp".to_owned()), (0x8847, "mpls".to_owned())];
-        FrameTypeMonitor {port_mapping, ip_lpm_entries, ethernet_types, statistics: FrameTypeStatistics::default() }
+        FrameTypeMonitor {port_mapping, ip_lpm_entries, ethernet_types, custom_types: BTreeMap::new(), next_class_id: 0, statistics: FrameTypeStatistics::default() }
+    }
+
+    /// Returns the user-defined frame types in the order they were added.
+    pub fn custom_types(&self) -> Vec<FrameTypeClassifier> {
+        self.custom_types.values().cloned().collect()
+    }
+
+    /// Validates a user-defined frame type before it is added.
+    pub fn validate_custom_type(&self, classifier: &FrameTypeClassifier) -> Result<(), String> {
+        if classifier.name.is_empty() {
+            return Err("Name of the frame type must not be empty.".to_owned());
+        }
+
+        if BUILT_IN_TYPES.contains(&classifier.name.as_str()) {
+            return Err(format!("Frame type {} is a built-in frame type.", classifier.name));
+        }
+
+        if self.custom_types.values().any(|c| c.name == classifier.name) {
+            return Err(format!("Frame type {} already exists.", classifier.name));
+        }
+
+        if classifier.ether_type.is_none() && classifier.ip_src.is_none() && classifier.ip_dst.is_none() {
+            return Err(format!("Frame type {} requires an EtherType or an IP prefix.", classifier.name));
+        }
+
+        if classifier.ip_src_prefix.is_some_and(|p| p > 32) || classifier.ip_dst_prefix.is_some_and(|p| p > 32) {
+            return Err(format!("Prefix length of frame type {} has to be at most 32.", classifier.name));
+        }
+
+        // one entry for the TX and one entry for the RX path per port
+        let entries = 2 * self.port_mapping.len() * (self.custom_types.len() + 1);
+
+        if entries > CUSTOM_TYPE_MONITOR_SIZE || self.next_class_id == u16::MAX {
+            return Err(format!("Too many user-defined frame types. The data plane supports at most {} table entries.", CUSTOM_TYPE_MONITOR_SIZE));
+        }
+
+        Ok(())
+    }
+
+    /// Adds a user-defined frame type and writes its entries to the [CUSTOM_TYPE_MONITOR] table.
+    /// The classifier has to be validated with [FrameTypeMonitor::validate_custom_type] before.
+    pub async fn add_custom_type(&mut self, switch: &SwitchConnection, classifier: FrameTypeClassifier) -> Result<(), RBFRTError> {
+        let class_id = self.next_class_id;
+
+        switch.write_table_entries(self.custom_type_entries(class_id, &classifier)).await?;
+
+        self.custom_types.insert(class_id, classifier);
+        self.next_class_id += 1;
+
+        Ok(())
+    }
+
+    /// Removes the user-defined frame type `name` and its entries from the [CUSTOM_TYPE_MONITOR] table.
+    /// Returns false if no such frame type exists.
+    pub async fn remove_custom_type(&mut self, switch: &SwitchConnection, name: &str) -> Result<bool, RBFRTError> {
+        let Some((class_id, classifier)) = self.custom_types.iter().find(|(_, c)| c.name == name).map(|(id, c)| (*id, c.clone())) else {
+            return Ok(false);
+        };
+
+        switch.delete_table_entries(self.custom_type_entries(class_id, &classifier)).await?;
+
+        self.custom_types.remove(&class_id);
+
+        Ok(true)
+    }
+
+    /// Builds the [CUSTOM_TYPE_MONITOR] entries of a user-defined frame type for the TX and RX path of each port.
+    fn custom_type_entries(&self, class_id: u16, classifier: &FrameTypeClassifier) -> Vec<table::Request> {
+        let ether_type = match classifier.ether_type {
+            Some(ether_type) => MatchValue::ternary(ether_type, 0xFFFF),
+            None => MatchValue::ternary(0u16, 0u16)
+        };
+
+        let ip_required = classifier.ip_src.is_some() || classifier.ip_dst.is_some();
+
+        let prefix = |ip: Option<Ipv4Addr>, length: Option<u8>| match ip {
+            Some(ip) => {
+                let mask = u32::MAX.checked_shl(32 - length.unwrap_or(32).min(32) as u32).unwrap_or(0);
+                MatchValue::ternary(u32::from(ip) & mask, mask)
+            }
+            None => MatchValue::ternary(0u32, 0u32)
+        };
+
+        let mut table_entries = vec![];
+
+        for mapping in self.port_mapping.values() {
+            for port in [mapping.tx_recirculation, mapping.rx_recirculation] {
+                // lower value, higher priority
+                table_entries.push(table::Request::new(CUSTOM_TYPE_MONITOR)
+                    .match_key("ig_intr_md.ingress_port", MatchValue::exact(port))
+                    .match_key("hdr.ethernet.ether_type", ether_type.clone())
+                    .match_key("hdr.inner_ipv4.$valid", if ip_required {MatchValue::ternary(1u8, 1u8)} else {MatchValue::ternary(0u8, 0u8)})
+                    .match_key("hdr.inner_ipv4.src_addr", prefix(classifier.ip_src, classifier.ip_src_prefix))
+                    .match_key("hdr.inner_ipv4.dst_addr", prefix(classifier.ip_dst, classifier.ip_dst_prefix))
+                    .match_key("$MATCH_PRIORITY", MatchValue::exact(class_id as u32 + 1))
+                    .action(&format!("{}.{}", ACTION_PREFIX, "custom"))
+                    .action_data("class_id", class_id));
+            }
+        }
+
+        table_entries
     }
 
     /// Configures the frame type monitor table in the ingress pipeline.
@@ -129,6 +274,12 @@ impl FrameTypeMonitor {
         info!("Configure table {}.", ETHERNET_TYPE_MONITOR);
         switch.write_table_entries(table_entries_ethernet_type).await?;
 
+        if !self.custom_types.is_empty() {
+            info!("Configure table {}.", CUSTOM_TYPE_MONITOR);
+            let table_entries_custom_type = self.custom_types.iter().flat_map(|(class_id, classifier)| self.custom_type_entries(*class_id, classifier)).collect();
+            switch.write_table_entries(table_entries_custom_type).await?;
+        }
+
         Ok(())
     }
 
@@ -148,13 +299,16 @@ impl FrameTypeMonitor {
                 stats.frame_type_data.insert(*port, TypeCount::default());
             }
 
-            for t in [FRAME_TYPE_MONITOR, ETHERNET_TYPE_MONITOR] {
+            // class id -> name of the user-defined frame types
+            let custom_names: HashMap<u16, String> = state.frame_type_monitor.lock().await.custom_types.iter().map(|(id, c)| (*id, c.name.clone())).collect();
+
+            for t in [FRAME_TYPE_MONITOR, ETHERNET_TYPE_MONITOR, CUSTOM_TYPE_MONITOR] {
                 let request = table::Request::new(t);
                 let sync = table::Request::new(t).operation(table::TableOperation::SyncCounters);
 
                 let entries = {
                     // sync counters
-                    let switch = &state.switch;
+                    let switch = &*state.switch.read().await;
 
                     if switch.execute_operation(sync).await.is_err() {
                         warn! {"Encountered error while synchronizing {}.", t};
@@ -185,6 +339,18 @@ impl FrameTypeMonitor {
                         frame_type = "qinq";
                     }
 
+                    // user-defined frame types are identified by their class id
+                    if frame_type == "custom" {
+                        let class_id = entry.action_data.iter()
+                            .find(|action| action.get_name() == "class_id")
+                            .map(|action| action.get_data().to_u32() as u16);
+
+                        match class_id.and_then(|id| custom_names.get(&id)) {
+                            Some(name) => frame_type = name,
+                            None => continue // removed in the meantime
+                        }
+                    }
+
                     let count = 'get_count: {
                         for action in &entry.action_data {
                             if action.get_name() == "$COUNTER_SPEC_PKTS" {
@@ -216,7 +382,7 @@ impl FrameTypeMonitor {
 
     /// Clear the frame type table
     pub async fn clear(&self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
-        switch.clear_tables(vec![FRAME_TYPE_MONITOR, ETHERNET_TYPE_MONITOR]).await?;
+        switch.clear_tables(vec![FRAME_TYPE_MONITOR, ETHERNET_TYPE_MONITOR, CUSTOM_TYPE_MONITOR]).await?;
 
         Ok(())
     }
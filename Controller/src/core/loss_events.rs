@@ -0,0 +1,364 @@
+/* Copyright 2022-present University of Tuebingen, Chair of Communication Networks
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
+ */
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::core::traffic_gen_core::helper::calculate_overhead;
+use crate::core::traffic_gen_core::types::Stream;
+use crate::core::traffic_gen_core::const_definitions::MAX_LOSS_EVENTS;
+
+/// Frame rate in frames per second of a generated `stream`.
+/// The traffic rate of the stream is expected in Gbps.
+pub fn frame_rate(stream: &Stream) -> f64 {
+    // preamble + inter frame gap (IFG) = 20 bytes
+    let frame_size = (stream.frame_size + calculate_overhead(stream) + 20) as f64;
+
+    stream.traffic_rate as f64 * 1e9 / (8f64 * frame_size)
+}
+
+/// Period of packet loss of a stream, e.g., during a failover.
+/// An outage starts when the RX byte counter of the stream stops increasing and ends when frames are received again.
+/// Loss is observed with the monitoring packets, the start and end are therefore
+/// accurate up to the monitoring interval.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LossEvent {
+    /// RX port of the stream
+    pub(crate) port: u32,
+    /// App id of the stream
+    pub(crate) app_id: u32,
+    /// Time of the first monitoring packet that observed no received frames of the stream in ms since the start of the traffic generation.
+    /// For loss without an outage, the time of the previous monitoring packet.
+    pub(crate) start: u64,
+    /// Time of the first monitoring packet that observed received frames again in ms since the start of the traffic generation.
+    /// Not set while the loss event is ongoing.
+    pub(crate) end: Option<u64>,
+    /// Duration in ms between `start` and `end`, accurate up to the monitoring interval.
+    /// Not set while the loss event is ongoing.
+    #[serde(default)]
+    pub(crate) duration: Option<u64>,
+    /// Number of lost frames.
+    /// The data plane detects lost frames with the first frame after the outage, they are therefore reported at its end.
+    pub(crate) lost_frames: u64,
+    /// Outage duration in ms, i.e., the lost frames divided by the frame rate of the stream.
+    /// Not set if the frame rate of the stream is unknown.
+    pub(crate) outage_duration: Option<f64>,
+}
+
+impl LossEvent {
+    /// Service disruption of the loss event in ms.
+    /// The outage duration computed from the frame rate is preferred over the duration between the timestamps.
+    pub fn disruption(&self) -> Option<f64> {
+        self.outage_duration.or(self.duration.map(|duration| duration as f64))
+    }
+}
+
+/// Thresholds of the service disruption measurement, e.g., for protection switching acceptance tests.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct DisruptionThresholds {
+    /// Loss events with fewer lost frames are not counted as outage
+    pub(crate) min_lost_frames: Option<u64>,
+    /// Loss events with a shorter disruption in ms are not counted as outage
+    pub(crate) min_outage: Option<f64>,
+    /// Maximal allowed disruption of an outage in ms, e.g., 50 ms for protection switching
+    pub(crate) max_disruption: Option<f64>,
+}
+
+/// Service disruption of the streams, derived from the loss events.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DisruptionReport {
+    /// Loss events that exceed the thresholds in the order of their start
+    pub(crate) outages: Vec<LossEvent>,
+    /// Sum of the disruption of all outages in ms.
+    /// Outages of several streams that overlap in time, e.g., of a single failover, are counted once with their maximal disruption.
+    pub(crate) total_disruption: f64,
+    /// Maximal disruption of an outage in ms
+    pub(crate) max_disruption: f64,
+    /// Indicates whether no outage exceeds `max_disruption` of the thresholds.
+    /// Not set if no maximal disruption is configured.
+    pub(crate) passed: Option<bool>,
+}
+
+impl DisruptionReport {
+    /// Creates the report of the loss `events` that exceed the `thresholds`.
+    pub fn new(events: &[LossEvent], thresholds: &DisruptionThresholds) -> DisruptionReport {
+        let outages: Vec<LossEvent> = events.iter()
+            .filter(|event| thresholds.min_lost_frames.map(|min| event.lost_frames >= min).unwrap_or(true))
+            .filter(|event| thresholds.min_outage.map(|min| event.disruption().unwrap_or_default() >= min).unwrap_or(true))
+            .cloned()
+            .collect();
+
+        let total_disruption = DisruptionReport::merged_disruption(&outages);
+        let max_disruption = outages.iter().filter_map(|event| event.disruption()).fold(0f64, f64::max);
+
+        DisruptionReport {
+            outages,
+            total_disruption,
+            max_disruption,
+            passed: thresholds.max_disruption.map(|max| max_disruption <= max),
+        }
+    }
+
+    /// Sums the disruption of the `outages`, where outages that overlap in time between start and end are merged.
+    /// A merged outage counts with the maximal disruption of its outages. Ongoing outages overlap with all later outages.
+    fn merged_disruption(outages: &[LossEvent]) -> f64 {
+        let mut intervals: Vec<(u64, u64, f64)> = outages.iter()
+            .filter_map(|event| event.disruption().map(|disruption| (event.start, event.end.unwrap_or(u64::MAX), disruption)))
+            .collect();
+
+        intervals.sort_by_key(|(start, _, _)| *start);
+
+        let mut total = 0f64;
+        let mut current: Option<(u64, f64)> = None;
+
+        for (start, end, disruption) in intervals {
+            current = match current {
+                Some((current_end, current_disruption)) if start < current_end => Some((current_end.max(end), current_disruption.max(disruption))),
+                _ => {
+                    total += current.map(|(_, disruption)| disruption).unwrap_or_default();
+                    Some((end, disruption))
+                }
+            };
+        }
+
+        total + current.map(|(_, disruption)| disruption).unwrap_or_default()
+    }
+}
+
+/// Counters of a stream at the previous monitoring digest
+#[derive(Debug, Clone, Copy)]
+struct StreamCounters {
+    /// RX byte counter of the stream
+    bytes: u64,
+    /// Packet loss counter of the stream
+    loss: u64,
+    /// Time of the monitoring digest in ms since the start of the traffic generation
+    time: u64,
+}
+
+/// Log of the loss events of all streams.
+///
+/// The log is built from the RX byte counters and packet loss counters of the monitoring digests.
+/// At most [MAX_LOSS_EVENTS] events are kept.
+#[derive(Debug, Clone, Default)]
+pub struct LossEventLog {
+    events: Vec<LossEvent>,
+    /// Index of the ongoing loss event per (port, app id)
+    ongoing: HashMap<(u32, u32), usize>,
+    /// Counters of the previous monitoring digest per (port, app id)
+    counters: HashMap<(u32, u32), StreamCounters>,
+    /// Frame rate in frames per second per (RX port, app id)
+    frame_rates: HashMap<(u32, u32), f64>,
+}
+
+impl LossEventLog {
+    /// Sets the frame rates in frames per second per (RX port, app id) that are used for the outage duration.
+    pub fn set_frame_rates(&mut self, frame_rates: HashMap<(u32, u32), f64>) {
+        self.frame_rates = frame_rates;
+    }
+
+    /// Updates the loss event of the stream (`port`, `app_id`) with the counters
+    /// of a monitoring digest at `time` ms since the start of the traffic generation.
+    ///
+    /// - `bytes`: RX byte counter of the stream
+    /// - `loss`: Packet loss counter of the stream
+    pub fn update(&mut self, port: u32, app_id: u32, bytes: u64, loss: u64, time: u64) {
+        let key = (port, app_id);
+        let previous = self.counters.insert(key, StreamCounters { bytes, loss, time });
+
+        let Some(previous) = previous else {
+            return;
+        };
+
+        // no frames are received before the stream arrives for the first time
+        if bytes == 0 {
+            return;
+        }
+
+        // the byte counter only stalls if no frame of the stream is received, a decrease is an overflow of the 48 bit register
+        let stalled = bytes == previous.bytes;
+        let frame_rate = self.frame_rates.get(&key).copied().filter(|rate| *rate > 0f64);
+
+        match self.ongoing.get(&key).copied() {
+            Some(index) => {
+                let event = &mut self.events[index];
+
+                // late packets are subtracted from the packet loss counter
+                event.lost_frames = (event.lost_frames + loss).saturating_sub(previous.loss);
+
+                if !stalled {
+                    event.end = Some(time);
+                    event.duration = Some(time.saturating_sub(event.start));
+                    self.ongoing.remove(&key);
+                }
+
+                event.outage_duration = frame_rate.map(|rate| 1000f64 * event.lost_frames as f64 / rate);
+            }
+            None => {
+                if self.events.len() >= MAX_LOSS_EVENTS {
+                    return;
+                }
+
+                if stalled {
+                    self.ongoing.insert(key, self.events.len());
+                    self.events.push(LossEvent {
+                        port,
+                        app_id,
+                        start: time,
+                        end: None,
+                        duration: None,
+                        lost_frames: 0,
+                        outage_duration: frame_rate.map(|_| 0f64),
+                    });
+                }
+                else if loss > previous.loss {
+                    // loss without an outage happened since the previous monitoring digest
+                    let lost_frames = loss - previous.loss;
+
+                    self.events.push(LossEvent {
+                        port,
+                        app_id,
+                        start: previous.time,
+                        end: Some(time),
+                        duration: Some(time.saturating_sub(previous.time)),
+                        lost_frames,
+                        outage_duration: frame_rate.map(|rate| 1000f64 * lost_frames as f64 / rate),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns the loss events in the order of their start.
+    pub fn events(&self) -> &[LossEvent] {
+        &self.events
+    }
+
+    /// Removes all loss events. The frame rates are kept.
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.ongoing.clear();
+        self.counters.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outage_starts_when_bytes_stall() {
+        let mut log = LossEventLog::default();
+        log.set_frame_rates(HashMap::from([((1, 1), 1000f64)]));
+
+        log.update(1, 1, 100, 0, 0);
+        log.update(1, 1, 200, 0, 500);
+        log.update(1, 1, 200, 0, 1000);
+        log.update(1, 1, 200, 0, 1500);
+
+        assert_eq!(log.events().len(), 1);
+        assert_eq!(log.events()[0].start, 1000);
+        assert_eq!(log.events()[0].end, None);
+
+        // the lost frames are reported with the first frames after the outage
+        log.update(1, 1, 300, 800, 2000);
+
+        let event = &log.events()[0];
+        assert_eq!(event.end, Some(2000));
+        assert_eq!(event.duration, Some(1000));
+        assert_eq!(event.lost_frames, 800);
+        assert_eq!(event.outage_duration, Some(800f64));
+        assert_eq!(log.events().len(), 1);
+    }
+
+    #[test]
+    fn loss_without_outage() {
+        let mut log = LossEventLog::default();
+
+        // stream has not arrived yet
+        log.update(1, 1, 0, 0, 0);
+        log.update(1, 1, 0, 0, 500);
+        assert!(log.events().is_empty());
+
+        log.update(1, 1, 100, 0, 1000);
+        log.update(1, 1, 200, 5, 1500);
+        log.update(1, 1, 300, 5, 2000);
+
+        assert_eq!(log.events().len(), 1);
+
+        let event = &log.events()[0];
+        assert_eq!(event.start, 1000);
+        assert_eq!(event.end, Some(1500));
+        assert_eq!(event.lost_frames, 5);
+        assert_eq!(event.outage_duration, None);
+    }
+
+    fn event(port: u32, start: u64, end: Option<u64>, lost_frames: u64, outage_duration: Option<f64>) -> LossEvent {
+        LossEvent { port, app_id: 1, start, end, duration: end.map(|end| end - start), lost_frames, outage_duration }
+    }
+
+    #[test]
+    fn report_thresholds() {
+        let events = [event(1, 0, Some(500), 1, Some(0.5)), event(1, 1000, Some(1500), 100, Some(20f64)), event(1, 2000, Some(2500), 300, Some(60f64))];
+
+        let report = DisruptionReport::new(&events, &DisruptionThresholds::default());
+        assert_eq!(report.outages.len(), 3);
+        assert_eq!(report.total_disruption, 80.5);
+        assert_eq!(report.max_disruption, 60f64);
+        assert_eq!(report.passed, None);
+
+        let report = DisruptionReport::new(&events, &DisruptionThresholds { min_lost_frames: Some(2), min_outage: None, max_disruption: Some(50f64) });
+        assert_eq!(report.outages.len(), 2);
+        assert_eq!(report.total_disruption, 80f64);
+        assert_eq!(report.passed, Some(false));
+
+        let report = DisruptionReport::new(&events, &DisruptionThresholds { min_lost_frames: None, min_outage: Some(1f64), max_disruption: Some(60f64) });
+        assert_eq!(report.outages.len(), 2);
+        assert_eq!(report.passed, Some(true));
+
+        let report = DisruptionReport::new(&events, &DisruptionThresholds { min_lost_frames: None, min_outage: Some(30f64), max_disruption: Some(60f64) });
+        assert_eq!(report.outages.len(), 1);
+        assert_eq!(report.total_disruption, 60f64);
+        assert_eq!(report.passed, Some(true));
+    }
+
+    #[test]
+    fn report_without_frame_rate() {
+        // the duration between start and end is used without frame rate
+        let events = [event(1, 1000, Some(1500), 10, None), event(1, 3000, None, 0, None)];
+
+        let report = DisruptionReport::new(&events, &DisruptionThresholds { min_lost_frames: None, min_outage: Some(100f64), max_disruption: Some(50f64) });
+        assert_eq!(report.outages.len(), 1);
+        assert_eq!(report.total_disruption, 500f64);
+        assert_eq!(report.max_disruption, 500f64);
+        assert_eq!(report.passed, Some(false));
+    }
+
+    #[test]
+    fn report_merges_overlapping_outages() {
+        // a single failover affects the streams of two ports, followed by a second failover
+        let events = [event(1, 1000, Some(2000), 40, Some(40f64)), event(2, 1010, Some(2010), 45, Some(45f64)), event(1, 5000, Some(5500), 30, Some(30f64))];
+
+        let report = DisruptionReport::new(&events, &DisruptionThresholds::default());
+        assert_eq!(report.outages.len(), 3);
+        assert_eq!(report.total_disruption, 75f64);
+        assert_eq!(report.max_disruption, 45f64);
+    }
+}
@@ -17,14 +17,19 @@
  * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
  */
 mod frame_size_monitor;
-pub use frame_size_monitor::FrameSizeMonitor;
+pub use frame_size_monitor::{FrameSizeBin, FrameSizeMonitor};
 
 mod frame_type_monitor;
 
-pub use frame_type_monitor::FrameTypeMonitor;
+pub use frame_type_monitor::{FrameTypeClassifier, FrameTypeMonitor};
+
+mod flow_monitor;
+pub use flow_monitor::{FlowMonitor, FLOW_MONITOR_SIZE};
 
 mod rate_monitor;
 pub mod statistics;
+pub mod sample_storage;
+pub mod loss_events;
 pub mod traffic_gen;
 
 mod multicast;
@@ -36,7 +41,7 @@ pub use rate_monitor::RateMonitor;
 
 pub mod traffic_gen_core;
 mod arp;
-mod config;
+pub mod config;
 
 pub use config::Config;
 
@@ -18,7 +18,7 @@
  */
 
 use std::cmp::max;
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -30,10 +30,13 @@ use rbfrt::register::Register;
 use rbfrt::table::{MatchValue, ToBytes};
 
 use crate::{AppState, PortMapping};
-use crate::core::traffic_gen_core::types::GenerationMode;
-use crate::core::statistics::{IATStatistics, RateMonitorStatistics, TimeStatistic};
+use crate::core::traffic_gen_core::types::{GenerationMode, Stream, StreamSetting};
+use crate::core::loss_events::{frame_rate, LossEventLog};
+use crate::core::sample_storage::{DelayVariation, SampleStorage};
+use crate::core::statistics::{HistogramConfig, IATStatistics, RateMonitorStatistics, ReorderDensityBin, ReorderStatistics, TimeStatistic};
 use crate::core::traffic_gen_core::event::TrafficGenEvent;
 use crate::core::traffic_gen_core::types::MonitoringMapping;
+use crate::core::traffic_gen_core::const_definitions::{DEFAULT_RTT_PERCENTILES, DEFAULT_SAMPLE_RATE, DIGEST_TIMEOUT, MONITORING_PACKET_INTERVAL, REORDER_EXTENT_BUCKETS, REORDER_EXTENT_TABLE};
 
 /// Table that checks if a packet was received on an ingress port
 const IS_INGRESS_TABLE: &str = "ingress.p4tg.is_ingress";
@@ -41,6 +44,12 @@ const MEAN_IAT_REGISTER: &str = "ingress.p4tg.iat.mean_iat";
 const MAE_IAT_REGISTER: &str = "ingress.p4tg.iat.mae_iat";
 const CURRENT_MEAN_IAT_REGISTER: &str = "ingress.p4tg.iat.current_mean_iat";
 
+/// Duplicate detection and reordering state per stream
+const SEQ_HISTORY_REGISTER: &str = "ingress.p4tg.seq_history";
+const DUPLICATES_REGISTER: &str = "ingress.p4tg.duplicates";
+const MAX_EXTENT_REGISTER: &str = "ingress.p4tg.max_extent";
+const REORDER_DENSITY_REGISTER: &str = "ingress.p4tg.reorder_density";
+
 /// Controls whether IATs should be monitored
 const MONITOR_IAT_TABLE: &str = "ingress.p4tg.monitor_iat";
 
@@ -52,18 +61,45 @@ const RATE_DIGEST_NAME: &str = "pipe.SwitchIngressDeparser.digest";
 
 const RTT_IAT_DIGEST_NAME: &str = "pipe.SwitchIngressDeparser.digest_2";
 
-/// Number of RTTs that should be stored
+/// Number of RTT / IAT samples per port that are kept for percentiles and histograms
 const RTT_STORAGE: usize = 50000;
 
+/// Number of RTT samples per port and stream that are kept for percentiles and histograms
+const APP_RTT_STORAGE: usize = 10000;
+
 /// This module handles the initialization of the `egress.frame_size_monitor` table
 /// that counts the different frame sizes that are received/sent
 pub struct RateMonitor {
     port_mapping: HashMap<u32, PortMapping>,
     pub statistics: RateMonitorStatistics,
     pub time_statistics: TimeStatistic,
-    pub rtt_storage: HashMap<u32, VecDeque<u64>>,
-    pub tx_iat_storage: HashMap<u32, VecDeque<u64>>,
-    pub rx_iat_storage: HashMap<u32, VecDeque<u64>>,
+    pub rtt_storage: HashMap<u32, SampleStorage>,
+    /// RFC 3550 jitter and RFC 5481 IPDV of the RTT samples
+    pub rtt_variation: HashMap<u32, DelayVariation>,
+    /// RTT samples per port and app id
+    pub app_rtt_storage: HashMap<u32, HashMap<u32, SampleStorage>>,
+    /// RFC 3550 jitter and RFC 5481 IPDV of the RTT samples per port and app id
+    pub app_rtt_variation: HashMap<u32, HashMap<u32, DelayVariation>>,
+    pub tx_iat_storage: HashMap<u32, SampleStorage>,
+    pub rx_iat_storage: HashMap<u32, SampleStorage>,
+    /// Loss events of the streams
+    pub loss_events: LossEventLog,
+    /// Mapping of the monitoring index to (port, app id)
+    index_mapping: HashMap<u32, MonitoringMapping>,
+    /// Maps the app id of a copy of a stream for a rate override to the app id of the stream per front panel port
+    app_aliases: HashMap<u32, HashMap<u32, u32>>,
+    /// Sets of RX ports that share the sequence number state in the data plane
+    rx_port_groups: Vec<Vec<u32>>,
+    /// Number of RTT samples per second and recirculation port
+    pub rtt_sample_rate: u32,
+    /// Number of IAT samples per second and recirculation port in sample mode
+    pub iat_sample_rate: u32,
+    /// Percentiles of the RTT that are reported in the statistics
+    pub rtt_percentiles: Vec<f64>,
+    /// Bucket layout of the RTT histogram that is reported in the statistics
+    pub rtt_histogram: HistogramConfig,
+    /// Interval in ns of the monitoring packets, see [TrafficGen::set_monitoring_interval](crate::core::TrafficGen::set_monitoring_interval)
+    pub monitoring_interval: u32,
     #[allow(dead_code)]
     running: bool,
 }
@@ -91,7 +127,86 @@ impl DataRate {
 
 impl RateMonitor {
     pub fn new(port_mapping: HashMap<u32, PortMapping>) -> RateMonitor {
-        RateMonitor { port_mapping, statistics: RateMonitorStatistics::default(), time_statistics: TimeStatistic::default(), rtt_storage: Default::default(), tx_iat_storage: Default::default(), rx_iat_storage: Default::default(), running: true }
+        RateMonitor { port_mapping, statistics: RateMonitorStatistics::default(), time_statistics: TimeStatistic::default(), rtt_storage: Default::default(), rtt_variation: Default::default(), app_rtt_storage: Default::default(), app_rtt_variation: Default::default(), tx_iat_storage: Default::default(), rx_iat_storage: Default::default(), loss_events: Default::default(), index_mapping: Default::default(), app_aliases: Default::default(), rx_port_groups: vec![], rtt_sample_rate: DEFAULT_SAMPLE_RATE, iat_sample_rate: DEFAULT_SAMPLE_RATE, rtt_percentiles: DEFAULT_RTT_PERCENTILES.to_vec(), rtt_histogram: HistogramConfig::default(), monitoring_interval: MONITORING_PACKET_INTERVAL, running: true }
+    }
+
+    /// Sets the sets of RX ports whose packet loss and out of order packets are accounted together.
+    /// Only sets with multiple RX ports are considered.
+    /// The rules are written to the switch on the next [TrafficGenEvent::on_start].
+    pub fn set_rx_port_groups(&mut self, tx_rx_mapping: &HashMap<u32, Vec<u32>>) {
+        self.rx_port_groups = tx_rx_mapping.values().filter(|rx_ports| rx_ports.len() > 1).cloned().collect();
+    }
+
+    /// Sets the generated streams.
+    ///
+    /// Copies of a stream for rate overrides (see [resolve_rate_overrides](crate::core::traffic_gen_core::planner::resolve_rate_overrides)) are generated with additional app ids.
+    /// Their statistics are reported under the app id of the stream on the TX port of their stream setting and its RX ports.
+    /// The app id of the stream is the first app id of its stream id in `streams`.
+    ///
+    /// # Arguments
+    ///
+    /// * `streams`: Generated streams including the copies for rate overrides
+    /// * `stream_settings`: Stream settings with the app id that generates them
+    /// * `tx_rx_mapping`: Mapping of TX port to the set of expected RX ports
+    pub fn set_streams(&mut self, streams: &[Stream], stream_settings: &[StreamSetting], tx_rx_mapping: &HashMap<u32, Vec<u32>>) {
+        let mut requested_app_ids: HashMap<u8, u32> = HashMap::new();
+
+        for s in streams {
+            requested_app_ids.entry(s.stream_id).or_insert(s.app_id as u32);
+        }
+
+        // (app id, app id of the stream) per front panel port
+        let mut port_apps: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
+        let mut frame_rates = HashMap::new();
+
+        for setting in stream_settings.iter().filter(|s| s.active) {
+            let Some(stream) = streams.iter().find(|s| setting.belongs_to(s)) else {
+                continue;
+            };
+
+            let apps = (stream.app_id as u32, requested_app_ids[&stream.stream_id]);
+            let rx_ports = tx_rx_mapping.get(&setting.port).cloned().unwrap_or_default();
+
+            for rx_port in &rx_ports {
+                frame_rates.insert((*rx_port, apps.1), frame_rate(stream));
+            }
+
+            for port in rx_ports.into_iter().chain([setting.port]) {
+                port_apps.entry(port).or_default().push(apps);
+            }
+        }
+
+        // a copy is only reported as its stream if the stream itself is not sent / received on the port
+        self.app_aliases = port_apps.into_iter().map(|(port, apps)| {
+            let aliases = apps.iter()
+                .filter(|(app_id, requested)| app_id != requested && !apps.iter().any(|(other, _)| other == requested))
+                .copied()
+                .collect();
+
+            (port, aliases)
+        }).collect();
+
+        self.loss_events.set_frame_rates(frame_rates);
+    }
+
+    /// Returns the app id under which the statistics of `app_id` on the front panel `port` are reported.
+    /// Returns `None` if the app id is replaced by a copy of its stream for a rate override on this port.
+    fn reported_app_id(&self, port: u32, app_id: u32) -> Option<u32> {
+        let Some(aliases) = self.app_aliases.get(&port) else {
+            return Some(app_id);
+        };
+
+        match aliases.get(&app_id) {
+            Some(requested) => Some(*requested),
+            None if aliases.values().any(|requested| *requested == app_id) => None,
+            None => Some(app_id)
+        }
+    }
+
+    /// Sets the mapping of the monitoring index to (port, app id).
+    /// The index of the RX path of a stream is used for its duplicate and reordering state.
+    pub fn set_index_mapping(&mut self, index_mapping: &HashMap<u32, MonitoringMapping>) {
+        self.index_mapping = index_mapping.clone();
     }
 
     pub async fn init_monitoring_rules(&self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
@@ -99,10 +214,29 @@ impl RateMonitor {
         let delete_request = table::Request::new(IS_INGRESS_TABLE);
         switch.delete_table_entry(delete_request).await?;
 
+        // all RX recirculation ports of a group use the sequence number state
+        // of the RX recirculation port of the first port in the group
+        let mut seq_index: HashMap<u32, u32> = HashMap::new();
+
+        for group in &self.rx_port_groups {
+            if let Some(first) = group.first().and_then(|port| self.port_mapping.get(port)) {
+                for port in group {
+                    seq_index.insert(*port, first.rx_recirculation);
+                }
+            }
+        }
+
         let mut entries = vec![];
 
-        for mapping in self.port_mapping.values() {
-            entries.push(table::Request::new(IS_INGRESS_TABLE).match_key("ig_intr_md.ingress_port", MatchValue::exact(mapping.rx_recirculation)).action("ingress.p4tg.nop"));
+        for (port, mapping) in &self.port_mapping {
+            let req = table::Request::new(IS_INGRESS_TABLE).match_key("ig_intr_md.ingress_port", MatchValue::exact(mapping.rx_recirculation));
+
+            let req = match seq_index.get(port) {
+                Some(index) => req.action("ingress.p4tg.set_seq_index").action_data("index", *index),
+                None => req.action("ingress.p4tg.nop")
+            };
+
+            entries.push(req);
         }
 
         switch.write_table_entries(entries).await?;
@@ -110,8 +244,11 @@ impl RateMonitor {
         Ok(())
     }
 
+    /// Initializes the RTT meter in the data plane.
+    /// The meter limits the number of RTT digests to `rtt_sample_rate` per second and recirculation port.
+    /// Deactivates the meter - labels everything red - if a packet was not received on a recirculation port.
     pub async fn init_rtt_meter(&self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
-        let target_pps = 800f32;
+        let target_pps = self.rtt_sample_rate as f32;
         let packet_size = (64 + 20) as f32;
 
         let mut valid_ports = vec![];
@@ -129,7 +266,7 @@ impl RateMonitor {
             let mut cbs = cir_kbps;
             let mut pbs = 2 * cbs;
 
-            if !valid_ports.contains(&x) {
+            if !valid_ports.contains(&x) || self.rtt_sample_rate == 0 {
                 cir_kbps = 0;
                 pir_kbps = 0;
                 cbs = 1;
@@ -148,12 +285,37 @@ impl RateMonitor {
         Ok(())
     }
 
+    /// Initializes the buckets of the reorder density in the data plane.
+    /// Each bucket covers a range of the reordering extent, see [REORDER_EXTENT_BUCKETS].
+    pub async fn init_reorder_buckets(&self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
+        switch.clear_tables(vec![REORDER_EXTENT_TABLE]).await?;
+
+        let mut entries = vec![];
+
+        for (bucket, (lower, upper)) in REORDER_EXTENT_BUCKETS.iter().enumerate() {
+            let req = table::Request::new(REORDER_EXTENT_TABLE)
+                .match_key("ig_md.reorder_extent", MatchValue::range(*lower, *upper))
+                .match_key("$MATCH_PRIORITY", MatchValue::exact(1))
+                .action("ingress.p4tg.set_extent_bucket")
+                .action_data("bucket", bucket as u8);
+
+            entries.push(req);
+        }
+
+        switch.write_table_entries(entries).await?;
+
+        info!("Configured reorder density buckets.");
+
+        Ok(())
+    }
+
     /// Initializes the IAT meter in the data plane.
+    /// The meter limits the number of IAT digests to `iat_sample_rate` per second and recirculation port.
     /// Deactivates the meter - labels everything red - if we are not in sample mode
     /// or if a packet was not received on a recirculation port.
     /// This is used to limit the number of digests that are created.
     pub async fn init_iat_meter(&self, switch: &SwitchConnection, sample_mode: bool) -> Result<(), RBFRTError> {
-        let target_pps = 800f32;
+        let target_pps = self.iat_sample_rate as f32;
         let packet_size = (64 + 20) as f32;
 
         let mut valid_ports = vec![];
@@ -172,7 +334,7 @@ impl RateMonitor {
             let mut pbs = 2 * cbs;
 
             // deactivate iat metering if sample mode is not activated
-            if !valid_ports.contains(&x) || !sample_mode {
+            if !valid_ports.contains(&x) || !sample_mode || self.iat_sample_rate == 0 {
                 cir_kbps = 0;
                 pir_kbps = 0;
                 cbs = 1;
@@ -227,7 +389,7 @@ impl RateMonitor {
 
         loop {
             let mean_iat_register = {
-                let switch = &state.switch;
+                let switch = &*state.switch.read().await;
                 let sync = table::Request::new(MEAN_IAT_REGISTER).operation(table::TableOperation::SyncRegister);
 
                 // sync register
@@ -247,7 +409,7 @@ impl RateMonitor {
             };
 
             let mae_iat_register = {
-                let switch = &state.switch;
+                let switch = &*state.switch.read().await;
                 let sync = table::Request::new(MAE_IAT_REGISTER).operation(table::TableOperation::SyncRegister);
 
                 // sync register
@@ -343,7 +505,7 @@ impl RateMonitor {
 
             // write register updates
             {
-                let switch = &state.switch;
+                let switch = &*state.switch.read().await;
                 if switch.write_register_entries(update_requests).await.is_err() {
                     warn!("Error in updating {} register.", CURRENT_MEAN_IAT_REGISTER);
                 }
@@ -353,9 +515,145 @@ impl RateMonitor {
         }
     }
 
+    /// Synchronizes the register `name` and retrieves the entries of `requests`.
+    async fn read_register(switch: &SwitchConnection, name: &str, requests: Vec<register::Request>) -> Register {
+        let sync = table::Request::new(name).operation(table::TableOperation::SyncRegister);
+
+        // sync register
+        if switch.execute_operation(sync).await.is_err() {
+            warn!("Error in synchronization for register {}.", name);
+        }
+
+        match switch.get_register_entries(requests).await {
+            Ok(r) => r,
+            Err(err) => {
+                warn!("Error in reading register {}. Error: {:#?}", name, err);
+                Register::new("default", HashMap::new())
+            }
+        }
+    }
+
+    /// Returns the value of the register `name` at `index` in the given `pipe`.
+    fn register_value(register: &Register, name: &str, index: u32, pipe: usize) -> u64 {
+        register.entries().get(&index)
+            .and_then(|entry| entry.get_data().get(&format!("{}.f1", name)).and_then(|values| values.get(pipe)))
+            .map(|value| value.to_u128() as u64)
+            .unwrap_or_default()
+    }
+
+    /// This method monitors the duplicate and reordering registers of the data plane.
+    /// It computes the duplicates, maximal reordering extent (RFC 4737) and
+    /// reorder density (RFC 5236) per stream and aggregates them per port.
+    /// It runs in a thread.
+    ///
+    /// - `state`: Thread-safe state object that holds the application state (including the switch connection)
+    pub async fn monitor_reordering(state: Arc<AppState>) {
+        let num_buckets = REORDER_EXTENT_BUCKETS.len() as u32;
+
+        loop {
+            // (stream index, pipe, port, app id) of the RX path of each stream
+            let streams: Vec<(u32, usize, u32, u32)> = {
+                let rate_monitor = state.rate_monitor.lock().await;
+
+                let rx_mapping: HashMap<u32, u32> = rate_monitor.port_mapping.iter()
+                    .map(|(port, mapping)| (mapping.rx_recirculation, *port))
+                    .collect();
+
+                rate_monitor.index_mapping.values()
+                    .filter(|mapping| mapping.app_id < 8) // app id 8 is only used as the last hop of the monitoring packet
+                    .filter_map(|mapping| rx_mapping.get(&mapping.port).map(|port| (mapping, *port)))
+                    .filter_map(|(mapping, port)| rate_monitor.reported_app_id(port, mapping.app_id as u32).map(|app_id| (mapping.index, (mapping.port >> 7) as usize, port, app_id)))
+                    .collect()
+            };
+
+            let mut duplicate_requests = vec![];
+            let mut extent_requests = vec![];
+            let mut density_requests = vec![];
+
+            for (index, _, _, _) in &streams {
+                duplicate_requests.push(register::Request::new(DUPLICATES_REGISTER).index(*index));
+                extent_requests.push(register::Request::new(MAX_EXTENT_REGISTER).index(*index));
+
+                for bucket in 0..num_buckets {
+                    density_requests.push(register::Request::new(REORDER_DENSITY_REGISTER).index(*index * num_buckets + bucket));
+                }
+            }
+
+            let (duplicate_register, extent_register, density_register) = {
+                let switch = &*state.switch.read().await;
+
+                (RateMonitor::read_register(switch, DUPLICATES_REGISTER, duplicate_requests).await,
+                 RateMonitor::read_register(switch, MAX_EXTENT_REGISTER, extent_requests).await,
+                 RateMonitor::read_register(switch, REORDER_DENSITY_REGISTER, density_requests).await)
+            };
+
+            let mut app_reordering: HashMap<u32, HashMap<u32, ReorderStatistics>> = HashMap::new();
+
+            for (index, pipe, port, app_id) in &streams {
+                let mut stats = ReorderStatistics {
+                    duplicates: RateMonitor::register_value(&duplicate_register, DUPLICATES_REGISTER, *index, *pipe),
+                    max_extent: RateMonitor::register_value(&extent_register, MAX_EXTENT_REGISTER, *index, *pipe) as u32,
+                    reorder_density: REORDER_EXTENT_BUCKETS.iter().enumerate().map(|(bucket, (lower, upper))| ReorderDensityBin {
+                        lower: *lower,
+                        upper: *upper,
+                        count: RateMonitor::register_value(&density_register, REORDER_DENSITY_REGISTER, *index * num_buckets + bucket as u32, *pipe),
+                        density: 0f64
+                    }).collect()
+                };
+
+                stats.normalize();
+
+                app_reordering.entry(*port).or_default().insert(*app_id, stats);
+            }
+
+            let monitoring_interval = state.rate_monitor.lock().await.monitoring_interval as u64;
+
+            let (elapsed_time, running) = {
+                let exp = state.experiment.lock().await;
+
+                if exp.running {
+                    (RateMonitor::time_series_key(exp.start.elapsed().unwrap_or(Duration::from_secs(0)), monitoring_interval), true)
+                }
+                else {
+                    (0, false)
+                }
+            };
+
+            {
+                let rate_monitor = &mut state.rate_monitor.lock().await;
+
+                for (port, apps) in &app_reordering {
+                    let stats = ReorderStatistics::aggregate(apps.values());
+
+                    if running {
+                        rate_monitor.time_statistics.duplicates.entry(*port).or_default().insert(elapsed_time, stats.duplicates);
+                        rate_monitor.time_statistics.max_extent.entry(*port).or_default().insert(elapsed_time, stats.max_extent);
+
+                        // remove potential old data
+                        rate_monitor.time_statistics.duplicates.entry(*port).or_default().retain(|key, _| *key <= elapsed_time);
+                        rate_monitor.time_statistics.max_extent.entry(*port).or_default().retain(|key, _| *key <= elapsed_time);
+
+                        for (app_id, app_stats) in apps {
+                            let series = rate_monitor.time_statistics.app_duplicates.entry(*port).or_default().entry(*app_id).or_default();
+                            series.insert(elapsed_time, app_stats.duplicates);
+                            series.retain(|key, _| *key <= elapsed_time);
+                        }
+                    }
+
+                    rate_monitor.statistics.reordering.insert(*port, stats);
+                }
+
+                rate_monitor.statistics.app_reordering = app_reordering;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
     /// Calculates the L1 and L2 rate given current values and a last measurement.
-    fn calculate_rate((current_byte_count_l1, current_byte_count_l2, current_tstmp): (u64, u64, u64), last_rate: &DataRate) -> DataRate {
-        if current_tstmp > (last_rate.timestamp + (Duration::from_secs(1).as_nanos() as u64)) {
+    /// Calculates the rate since `last_rate` if at least `min_time_diff` ns elapsed.
+    fn calculate_rate((current_byte_count_l1, current_byte_count_l2, current_tstmp): (u64, u64, u64), last_rate: &DataRate, min_time_diff: u64) -> DataRate {
+        if current_tstmp > (last_rate.timestamp + min_time_diff) {
             let time_diff: f64 = (current_tstmp - last_rate.timestamp) as f64;
             let byte_diff_l1: f64 = (current_byte_count_l1 - last_rate.byte_count_l1) as f64;
 
@@ -372,8 +670,16 @@ impl RateMonitor {
         }
     }
 
+    /// Key of the time series for the `elapsed` time since the start of the experiment.
+    /// The key is in ms and aligned to the `monitoring_interval` (in ns) s.t. the digests of a monitoring packet share the key.
+    pub fn time_series_key(elapsed: Duration, monitoring_interval: u64) -> u32 {
+        let interval = max(monitoring_interval / 1_000_000, 1);
+
+        (elapsed.as_millis() as u64 / interval * interval) as u32
+    }
+
     /// Monitors the digests that are received from the switch.
-    /// This method runs in a thread.
+    /// This method runs in a thread and returns if the connection to the switch is lost.
     pub async fn monitor_digests(state: Arc<AppState>, index_mapping: &HashMap<u32, MonitoringMapping>, sample_mode: bool) {
         // Key: DataRate
         let mut last_tx: HashMap<u32, DataRate> = HashMap::new();
@@ -421,18 +727,44 @@ impl RateMonitor {
         }
 
         // listen on the channel that receives digests
-        while let Ok(digest) = &mut state.switch.digest_queue.recv() {
-            let (elapsed_time, running) = {
+        // the switch is not locked while waiting, s.t. the connection can be replaced
+        let digest_queue = state.switch.read().await.digest_queue.clone();
+
+        loop {
+            let digest = match tokio::task::block_in_place(|| digest_queue.recv_timeout(Duration::from_secs(DIGEST_TIMEOUT))) {
+                Ok(digest) => digest,
+                Err(err) if err.is_timeout() => {
+                    // monitoring packets arrive at least every few seconds, check if the switch still responds
+                    let request = table::Request::new(IS_INGRESS_TABLE);
+                    let alive = tokio::time::timeout(Duration::from_secs(DIGEST_TIMEOUT), async { state.switch.read().await.get_table_entry(request).await }).await;
+
+                    if matches!(alive, Ok(Ok(_))) {
+                        continue;
+                    }
+
+                    warn!("No digests received for {} seconds and the switch does not respond.", DIGEST_TIMEOUT);
+                    break;
+                }
+                Err(_) => break
+            };
+
+            let (elapsed, running) = {
                 let exp = state.experiment.lock().await;
 
                 if exp.running {
-                    (exp.start.elapsed().unwrap_or(Duration::from_secs(0)).as_secs() as u32, true)
+                    (exp.start.elapsed().unwrap_or(Duration::from_secs(0)), true)
                 }
                 else {
-                    (0, false)
+                    (Duration::from_secs(0), false)
                 }
             };
 
+            let monitoring_interval = state.rate_monitor.lock().await.monitoring_interval as u64;
+            let elapsed_time = RateMonitor::time_series_key(elapsed, monitoring_interval);
+
+            // the monitoring packets arrive each monitoring interval with some jitter
+            let min_time_diff = monitoring_interval * 9 / 10;
+
             if digest.name == RATE_DIGEST_NAME {
 
                 let data = &digest.data;
@@ -464,6 +796,41 @@ impl RateMonitor {
 
                 let is_tx = tx_reverse_mapping.contains_key(&port);
 
+                // packet loss and out of order packets are tracked per stream
+                // the RX digest carries the index of the next app, therefore subtract 1
+                // the values of the port are the sum over its streams
+                let (packet_loss, out_of_order) = if is_tx {
+                    (packet_loss, out_of_order)
+                } else {
+                    let rx_port = rx_reverse_mapping.get(&port).unwrap();
+                    let rate_monitor = &mut state.rate_monitor.lock().await;
+
+                    let app_id = app_index.checked_sub(1)
+                        .and_then(|index| index_mapping.get(&index))
+                        .and_then(|mapping| rate_monitor.reported_app_id(*rx_port, mapping.app_id as u32));
+
+                    if let Some(app_id) = app_id {
+                        if running {
+                            rate_monitor.loss_events.update(*rx_port, app_id, app_byte, packet_loss, elapsed.as_millis() as u64);
+                        }
+
+                        rate_monitor.statistics.app_packet_loss.entry(*rx_port).or_default().insert(app_id, packet_loss);
+                        rate_monitor.statistics.app_out_of_order.entry(*rx_port).or_default().insert(app_id, out_of_order);
+
+                        if running {
+                            rate_monitor.time_statistics.app_packet_loss.entry(*rx_port).or_default().entry(app_id).or_default().insert(elapsed_time, packet_loss);
+                            rate_monitor.time_statistics.app_out_of_order.entry(*rx_port).or_default().entry(app_id).or_default().insert(elapsed_time, out_of_order);
+
+                            // remove potential old data
+                            rate_monitor.time_statistics.app_packet_loss.entry(*rx_port).or_default().entry(app_id).or_default().retain(|key, _| *key <= elapsed_time);
+                            rate_monitor.time_statistics.app_out_of_order.entry(*rx_port).or_default().entry(app_id).or_default().retain(|key, _| *key <= elapsed_time);
+                        }
+                    }
+
+                    (rate_monitor.statistics.app_packet_loss.get(rx_port).map(|apps| apps.values().sum()).unwrap_or(0),
+                     rate_monitor.statistics.app_out_of_order.get(rx_port).map(|apps| apps.values().sum()).unwrap_or(0))
+                };
+
                 let last_update = if is_tx { &mut last_tx } else { &mut last_rx };
                 let last_update_app = if is_tx { &mut last_app_tx } else { &mut last_app_rx };
 
@@ -475,7 +842,7 @@ impl RateMonitor {
                 let index_port_app_mapping = index_mapping.get(&app_index);
 
                 if last.timestamp != 0 {
-                    let new_rate = RateMonitor::calculate_rate((l1_byte, l2_byte, time), last);
+                    let new_rate = RateMonitor::calculate_rate((l1_byte, l2_byte, time), last, min_time_diff);
 
                     if is_tx {
                         state.rate_monitor.lock().await.statistics.tx_rate_l1.insert(*port, new_rate.rate_l1);
@@ -523,13 +890,13 @@ impl RateMonitor {
                     let last_app = last_update_app.get(&app_index).unwrap();
 
                     if last_app.timestamp != 0 && last_app.byte_count_l2 <= app_byte { // catch overflow of 48 bit stream byte register
-                        let new_app_rate = RateMonitor::calculate_rate((0, app_byte, time), last_app);
+                        let new_app_rate = RateMonitor::calculate_rate((0, app_byte, time), last_app, min_time_diff);
                         let mapping = index_mapping.get(&app_index).unwrap();
+                        let rate_monitor = &mut state.rate_monitor.lock().await;
 
-                        if is_tx {
-                            state.rate_monitor.lock().await.statistics.app_tx_l2.get_mut(port).unwrap().insert(mapping.app_id as u32, new_app_rate.rate_l2);
-                        } else {
-                            state.rate_monitor.lock().await.statistics.app_rx_l2.get_mut(port).unwrap().insert(mapping.app_id as u32, new_app_rate.rate_l2);
+                        if let Some(app_id) = rate_monitor.reported_app_id(*port, mapping.app_id as u32) {
+                            let app_rates = if is_tx { &mut rate_monitor.statistics.app_tx_l2 } else { &mut rate_monitor.statistics.app_rx_l2 };
+                            app_rates.get_mut(port).unwrap().insert(app_id, new_app_rate.rate_l2);
                         }
 
                         last_update_app.insert(app_index, new_app_rate);
@@ -546,12 +913,25 @@ impl RateMonitor {
 
                 let rtt = data.get("rtt").unwrap().to_u64();
 
+                let app_id = data.get("app_id").unwrap().to_u32();
+
 
                 // catch timestamp overflow
                 if rtt > 0 && rtt < (u32::MAX / 2) as u64 && rx_reverse_mapping.contains_key(&port) {
                     let port = rx_reverse_mapping.get(&port).unwrap();
 
-                    state.rate_monitor.lock().await.rtt_storage.entry(*port).or_insert(VecDeque::with_capacity(RTT_STORAGE)).push_back(rtt);
+                    state.rate_monitor.lock().await.rtt_storage.entry(*port).or_insert_with(|| SampleStorage::new(RTT_STORAGE)).push(rtt);
+                    state.rate_monitor.lock().await.rtt_variation.entry(*port).or_default().push(rtt);
+
+                    // app id 0 is not used by streams
+                    if app_id != 0 {
+                        let rate_monitor = &mut state.rate_monitor.lock().await;
+
+                        if let Some(app_id) = rate_monitor.reported_app_id(*port, app_id) {
+                            rate_monitor.app_rtt_storage.entry(*port).or_default().entry(app_id).or_insert_with(|| SampleStorage::new(APP_RTT_STORAGE)).push(rtt);
+                            rate_monitor.app_rtt_variation.entry(*port).or_default().entry(app_id).or_default().push(rtt);
+                        }
+                    }
                     state.rate_monitor.lock().await.time_statistics.rtt.entry(*port).or_insert(BTreeMap::default()).insert(elapsed_time, rtt);
 
                     // remove potential old data
@@ -568,10 +948,10 @@ impl RateMonitor {
                         if iat > 0 && iat < (u32::MAX / 2) as u64 { // catch overflow
                             if rx_reverse_mapping.contains_key(&port) {
                                 let port = rx_reverse_mapping.get(&port).unwrap();
-                                state.rate_monitor.lock().await.rx_iat_storage.entry(*port).or_insert(VecDeque::with_capacity(RTT_STORAGE)).push_back(iat);
+                                state.rate_monitor.lock().await.rx_iat_storage.entry(*port).or_insert_with(|| SampleStorage::new(RTT_STORAGE)).push(iat);
                             } else if tx_reverse_mapping.contains_key(&port) {
                                 let port = tx_reverse_mapping.get(&port).unwrap();
-                                state.rate_monitor.lock().await.tx_iat_storage.entry(*port).or_insert(VecDeque::with_capacity(RTT_STORAGE)).push_back(iat);
+                                state.rate_monitor.lock().await.tx_iat_storage.entry(*port).or_insert_with(|| SampleStorage::new(RTT_STORAGE)).push(iat);
                             }
                         }
                     }
@@ -617,13 +997,22 @@ impl TrafficGenEvent for RateMonitor {
     /// Reset the state.
     async fn on_reset(&mut self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
         self.rtt_storage.clear();
+        self.rtt_variation.clear();
+        self.app_rtt_storage.clear();
+        self.app_rtt_variation.clear();
         self.tx_iat_storage.clear();
         self.rx_iat_storage.clear();
         self.time_statistics.tx_rate_l1.clear();
         self.time_statistics.rx_rate_l1.clear();
         self.time_statistics.packet_loss.clear();
         self.time_statistics.out_of_order.clear();
+        self.time_statistics.app_packet_loss.clear();
+        self.time_statistics.app_out_of_order.clear();
+        self.time_statistics.duplicates.clear();
+        self.time_statistics.app_duplicates.clear();
+        self.time_statistics.max_extent.clear();
         self.time_statistics.rtt.clear();
+        self.loss_events.clear();
 
         let monitoring_registers = vec!["ingress.p4tg.rx_seq",
                                         "egress.tx_seq",
@@ -633,6 +1022,10 @@ impl TrafficGenEvent for RateMonitor {
                                         "ingress.p4tg.out_of_order.reg_lo",
                                         "ingress.p4tg.out_of_order.reg_lo_carry",
                                         "ingress.p4tg.out_of_order.reg_hi",
+                                        SEQ_HISTORY_REGISTER,
+                                        DUPLICATES_REGISTER,
+                                        MAX_EXTENT_REGISTER,
+                                        REORDER_DENSITY_REGISTER,
                                         "ingress.p4tg.iat.mae_iat",
                                         "ingress.p4tg.iat.mean_iat",
         ];
@@ -0,0 +1,194 @@
+/* Copyright 2022-present University of Tuebingen, Chair of Communication Networks
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
+ */
+
+use std::sync::Arc;
+
+use rand::Rng;
+
+/// Bounded storage for RTT / IAT samples.
+///
+/// Min, max, mean and variance are computed over all samples in a streaming fashion (Welford's algorithm).
+/// Additionally, a uniform random subset of at most `capacity` samples is kept (reservoir sampling)
+/// that is used for percentiles and histograms.
+/// A sorted snapshot of the reservoir is cached and only rebuilt if new samples arrived.
+/// The memory consumption is therefore independent of the duration of the traffic generation.
+#[derive(Debug, Clone)]
+pub struct SampleStorage {
+    /// Uniform random subset of all samples
+    reservoir: Vec<u64>,
+    /// Maximal number of samples in the reservoir
+    capacity: usize,
+    /// Number of samples seen so far
+    n: u64,
+    min: u64,
+    max: u64,
+    mean: f64,
+    /// Sum of squared differences from the mean
+    m2: f64,
+    /// Most recent sample
+    last: u64,
+    /// Reservoir in ascending order, rebuilt on demand
+    sorted: Arc<Vec<u64>>,
+    /// Indicates whether `sorted` is outdated
+    dirty: bool,
+}
+
+impl SampleStorage {
+    pub fn new(capacity: usize) -> SampleStorage {
+        SampleStorage { reservoir: Vec::with_capacity(capacity), capacity, n: 0, min: 0, max: 0, mean: 0f64, m2: 0f64, last: 0, sorted: Default::default(), dirty: false }
+    }
+
+    /// Adds a sample to the running statistics and the reservoir.
+    pub fn push(&mut self, value: u64) {
+        self.n += 1;
+
+        if self.n == 1 {
+            self.min = value;
+            self.max = value;
+        }
+        else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+
+        let delta = value as f64 - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (value as f64 - self.mean);
+
+        self.last = value;
+        self.dirty = true;
+
+        if self.reservoir.len() < self.capacity {
+            self.reservoir.push(value);
+        }
+        else {
+            // replace a random sample with probability capacity / n
+            let index = rand::thread_rng().gen_range(0..self.n);
+
+            if index < self.capacity as u64 {
+                self.reservoir[index as usize] = value;
+            }
+        }
+    }
+
+    /// Number of samples seen so far
+    pub fn count(&self) -> u64 {
+        self.n
+    }
+
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Sample standard deviation
+    pub fn std(&self) -> f64 {
+        if self.n < 2 {
+            return 0f64;
+        }
+
+        (self.m2 / (self.n - 1) as f64).sqrt()
+    }
+
+    pub fn last(&self) -> u64 {
+        self.last
+    }
+
+    /// Returns the samples of the reservoir in ascending order.
+    /// The snapshot is only sorted again if samples were added since the last call.
+    pub fn sorted_samples(&mut self) -> Arc<Vec<u64>> {
+        if self.dirty {
+            let mut sorted = self.reservoir.clone();
+            sorted.sort_unstable();
+            self.sorted = Arc::new(sorted);
+            self.dirty = false;
+        }
+
+        Arc::clone(&self.sorted)
+    }
+}
+
+/// Streaming delay variation metrics of consecutive delay samples.
+///
+/// As the RTT digests are rate limited by the data plane, consecutive samples
+/// are not necessarily consecutive packets.
+#[derive(Debug, Clone, Default)]
+pub struct DelayVariation {
+    /// Previous delay sample
+    last: Option<u64>,
+    /// Interarrival jitter according to RFC 3550, Section 6.4.1
+    jitter: f64,
+    /// Number of IPDV values
+    n: u64,
+    /// Sum of the absolute IPDV values
+    abs_sum: u64,
+    min: i64,
+    max: i64,
+}
+
+impl DelayVariation {
+    /// Adds a delay sample.
+    pub fn push(&mut self, delay: u64) {
+        if let Some(last) = self.last {
+            // IPDV according to RFC 5481, Section 4.1
+            let ipdv = delay as i64 - last as i64;
+
+            // J(i) = J(i-1) + (|D(i-1,i)| - J(i-1))/16
+            self.jitter += (ipdv.unsigned_abs() as f64 - self.jitter) / 16f64;
+
+            if self.n == 0 {
+                self.min = ipdv;
+                self.max = ipdv;
+            }
+            else {
+                self.min = self.min.min(ipdv);
+                self.max = self.max.max(ipdv);
+            }
+
+            self.n += 1;
+            self.abs_sum += ipdv.unsigned_abs();
+        }
+
+        self.last = Some(delay);
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    /// Mean of the absolute IPDV values
+    pub fn ipdv_mean(&self) -> f64 {
+        self.abs_sum as f64 / self.n.max(1) as f64
+    }
+
+    pub fn ipdv_min(&self) -> i64 {
+        self.min
+    }
+
+    pub fn ipdv_max(&self) -> i64 {
+        self.max
+    }
+}
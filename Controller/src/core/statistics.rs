@@ -78,6 +78,70 @@ pub struct TypeCount {
     pub(crate) rx: HashMap<String, u128>
 }
 
+#[derive(Serialize, Clone, ToSchema)]
+pub struct FlowStatistics {
+    /// Flow counters per RX port and flow name
+    pub(crate) flow_rx: HashMap<u32, HashMap<String, FlowCounter>>
+}
+
+/// Stores the counters and rates of a flow in analyze mode.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct FlowCounter {
+    /// Number of received packets
+    pub(crate) packets: u128,
+    /// Number of received bytes
+    pub(crate) bytes: u128,
+    /// Packet rate in packets per second
+    pub(crate) packet_rate: f64,
+    /// L2 rate in bits per second
+    pub(crate) rate_l2: f64
+}
+
+/// Stores the statistics of a set of RX ports of a TX port, e.g., for LAG or ECMP tests.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RxGroupStatistics {
+    /// RX ports of the set
+    pub(crate) rx_ports: Vec<u32>,
+    /// Number of lost packets across all RX ports of the set
+    pub(crate) packet_loss: u64,
+    /// Number of out of order packets across all RX ports of the set
+    pub(crate) out_of_order: u64,
+    /// L1 receive rate of the set
+    pub(crate) rx_rate_l1: f64,
+    /// L2 receive rate of the set
+    pub(crate) rx_rate_l2: f64,
+    /// Share of each RX port on the L2 receive rate of the set
+    pub(crate) share: HashMap<u32, f64>
+}
+
+impl RxGroupStatistics {
+    /// Aggregates the per port statistics of the `rx_ports`.
+    pub fn new(rx_ports: &[u32], statistics: &RateMonitorStatistics) -> RxGroupStatistics {
+        let sum = |values: &HashMap<u32, f64>| rx_ports.iter().map(|port| values.get(port).copied().unwrap_or_default()).sum::<f64>();
+
+        let rx_rate_l1 = sum(&statistics.rx_rate_l1);
+        let rx_rate_l2 = sum(&statistics.rx_rate_l2);
+
+        let share = rx_ports.iter().map(|port| {
+            let rate = statistics.rx_rate_l2.get(port).copied().unwrap_or_default();
+            (*port, if rx_rate_l2 > 0f64 { rate / rx_rate_l2 } else { 0f64 })
+        }).collect();
+
+        RxGroupStatistics {
+            rx_ports: rx_ports.to_vec(),
+            packet_loss: rx_ports.iter().map(|port| statistics.packet_loss.get(port).copied().unwrap_or_default()).sum(),
+            out_of_order: rx_ports.iter().map(|port| statistics.out_of_order.get(port).copied().unwrap_or_default()).sum(),
+            rx_rate_l1,
+            rx_rate_l2,
+            share
+        }
+    }
+}
+
+impl FlowStatistics {
+    pub fn default() -> FlowStatistics { FlowStatistics { flow_rx: HashMap::new() }}
+}
+
 impl FrameTypeStatistics {
     pub fn default() -> FrameTypeStatistics { FrameTypeStatistics { frame_type_data: HashMap::new() }}
 }
@@ -97,7 +161,11 @@ pub struct RateMonitorStatistics {
     pub(crate) app_tx_l2: HashMap<u32, HashMap<u32, f64>>,
     pub(crate) app_rx_l2: HashMap<u32, HashMap<u32, f64>>,
     pub(crate) packet_loss: HashMap<u32, u64>,
-    pub(crate) out_of_order: HashMap<u32, u64>
+    pub(crate) out_of_order: HashMap<u32, u64>,
+    pub(crate) app_packet_loss: HashMap<u32, HashMap<u32, u64>>,
+    pub(crate) app_out_of_order: HashMap<u32, HashMap<u32, u64>>,
+    pub(crate) reordering: HashMap<u32, ReorderStatistics>,
+    pub(crate) app_reordering: HashMap<u32, HashMap<u32, ReorderStatistics>>
 }
 
 impl RateMonitorStatistics {
@@ -113,10 +181,73 @@ impl RateMonitorStatistics {
             app_rx_l2: Default::default(),
             packet_loss: Default::default(),
             out_of_order: Default::default(),
+            app_packet_loss: Default::default(),
+            app_out_of_order: Default::default(),
+            reordering: Default::default(),
+            app_reordering: Default::default(),
+        }
+    }
+}
+
+/// Duplicates and reordering (RFC 4737 / RFC 5236) of the received P4TG traffic.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct ReorderStatistics {
+    /// Number of duplicate packets.
+    /// Duplicates are detected within the last 64 sequence numbers of a stream.
+    pub(crate) duplicates: u64,
+    /// Maximal reordering extent (RFC 4737), i.e., the maximal number of packets
+    /// with a higher sequence number that were received before a late packet.
+    pub(crate) max_extent: u32,
+    /// Reorder density (RFC 5236) of the received packets over the reordering extent.
+    /// Packets that are not late have an extent of 0.
+    pub(crate) reorder_density: Vec<ReorderDensityBin>
+}
+
+impl ReorderStatistics {
+    /// Aggregates the statistics of multiple streams, e.g., of all streams of a port.
+    pub fn aggregate<'a>(statistics: impl Iterator<Item=&'a ReorderStatistics>) -> ReorderStatistics {
+        let mut result = ReorderStatistics::default();
+
+        for stats in statistics {
+            result.duplicates += stats.duplicates;
+            result.max_extent = result.max_extent.max(stats.max_extent);
+
+            if result.reorder_density.is_empty() {
+                result.reorder_density = stats.reorder_density.clone();
+            }
+            else {
+                for (bin, other) in result.reorder_density.iter_mut().zip(&stats.reorder_density) {
+                    bin.count += other.count;
+                }
+            }
+        }
+
+        result.normalize();
+
+        result
+    }
+
+    /// Recomputes the density of each bucket from the packet counts.
+    pub fn normalize(&mut self) {
+        let total: u64 = self.reorder_density.iter().map(|bin| bin.count).sum();
+
+        for bin in self.reorder_density.iter_mut() {
+            bin.density = if total > 0 { bin.count as f64 / total as f64 } else { 0f64 };
         }
     }
 }
 
+/// Bucket of the reorder density that counts the packets with a reordering extent in [lower, upper].
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ReorderDensityBin {
+    pub(crate) lower: u32,
+    pub(crate) upper: u32,
+    /// Number of received packets in this bucket
+    pub(crate) count: u64,
+    /// Share of the received packets in this bucket
+    pub(crate) density: f64
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct IATStatistics {
     /// TX path
@@ -131,8 +262,76 @@ pub struct RTTStatistics {
     pub(crate) min: u32,
     pub(crate) max: u32,
     pub(crate) current: u32,
+    /// Standard deviation of the RTT in ns
     pub(crate) jitter: f64,
-    pub(crate) n: u32
+    pub(crate) n: u32,
+    /// Interarrival jitter according to RFC 3550 of consecutive RTT samples in ns
+    #[serde(default)]
+    pub(crate) rfc3550_jitter: f64,
+    /// Mean absolute inter-packet delay variation (IPDV, RFC 5481) of consecutive RTT samples in ns
+    #[serde(default)]
+    pub(crate) ipdv_mean: f64,
+    /// Minimal IPDV in ns
+    #[serde(default)]
+    pub(crate) ipdv_min: i64,
+    /// Maximal IPDV in ns
+    #[serde(default)]
+    pub(crate) ipdv_max: i64,
+    /// Packet delay variation (PDV, RFC 5481), i.e., the 99.9th percentile minus the minimal RTT in ns
+    #[serde(default)]
+    pub(crate) pdv: u32,
+    /// Configured percentiles of the RTT in ns.
+    /// The key is the percentile, e.g., "99.9".
+    #[serde(default)]
+    pub(crate) percentiles: BTreeMap<String, u32>,
+    /// Histogram of the RTT according to the configured [HistogramConfig].
+    #[serde(default)]
+    pub(crate) histogram: Vec<HistogramBin>
+}
+
+/// Bucket layout of a histogram.
+/// Values below `min` are counted in the first bucket, values above `max` in the last bucket.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HistogramConfig {
+    /// Lower bound of the first bucket in ns
+    pub(crate) min: u64,
+    /// Upper bound of the last bucket in ns
+    pub(crate) max: u64,
+    /// Number of buckets
+    pub(crate) num_bins: u32,
+    /// Logarithmically sized buckets instead of equally sized buckets
+    pub(crate) logarithmic: bool
+}
+
+impl Default for HistogramConfig {
+    fn default() -> Self {
+        // 10 buckets per decade from 1 us to 10 ms
+        HistogramConfig { min: 1000, max: 10000000, num_bins: 40, logarithmic: true }
+    }
+}
+
+impl HistogramConfig {
+    /// Returns the `num_bins + 1` bucket boundaries.
+    pub fn edges(&self) -> Vec<u64> {
+        (0..=self.num_bins).map(|i| {
+            let fraction = i as f64 / self.num_bins as f64;
+
+            if self.logarithmic {
+                (self.min as f64 * (self.max as f64 / self.min as f64).powf(fraction)).round() as u64
+            }
+            else {
+                (self.min as f64 + (self.max - self.min) as f64 * fraction).round() as u64
+            }
+        }).collect()
+    }
+}
+
+/// Bucket of a histogram that counts the values in [lower, upper).
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HistogramBin {
+    pub(crate) lower: u64,
+    pub(crate) upper: u64,
+    pub(crate) count: u64
 }
 
 impl IATStatistics {
@@ -175,6 +374,21 @@ pub struct TimeStatistic {
     pub(crate) rx_rate_l1: BTreeMap<u32, BTreeMap<u32, f64>>,
     pub(crate) packet_loss: BTreeMap<u32, BTreeMap<u32, u64>>,
     pub(crate) out_of_order: BTreeMap<u32, BTreeMap<u32, u64>>,
+    /// Packet loss per port and stream, the second key corresponds to the app_id
+    #[serde(default)]
+    pub(crate) app_packet_loss: BTreeMap<u32, BTreeMap<u32, BTreeMap<u32, u64>>>,
+    /// Out of order packets per port and stream, the second key corresponds to the app_id
+    #[serde(default)]
+    pub(crate) app_out_of_order: BTreeMap<u32, BTreeMap<u32, BTreeMap<u32, u64>>>,
+    /// Duplicate packets per port
+    #[serde(default)]
+    pub(crate) duplicates: BTreeMap<u32, BTreeMap<u32, u64>>,
+    /// Duplicate packets per port and stream, the second key corresponds to the app_id
+    #[serde(default)]
+    pub(crate) app_duplicates: BTreeMap<u32, BTreeMap<u32, BTreeMap<u32, u64>>>,
+    /// Maximal reordering extent per port
+    #[serde(default)]
+    pub(crate) max_extent: BTreeMap<u32, BTreeMap<u32, u32>>,
     pub(crate) rtt: BTreeMap<u32, BTreeMap<u32, u64>>,
     /// Save previous time statistics, where the key is the test number of the statistics. 
     /// Skip serializing if there are no previous time statistics.
@@ -189,6 +403,11 @@ impl TimeStatistic {
             rx_rate_l1: Default::default(),
             packet_loss: Default::default(),
             out_of_order: Default::default(),
+            app_packet_loss: Default::default(),
+            app_out_of_order: Default::default(),
+            duplicates: Default::default(),
+            app_duplicates: Default::default(),
+            max_extent: Default::default(),
             rtt: Default::default(),
             previous_time_statistics: Default::default(),
         }
@@ -17,7 +17,8 @@
  * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
  */
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::Ipv4Addr;
 use std::str::FromStr;
 use std::cmp;
 
@@ -31,11 +32,12 @@ use crate::core::{create_simple_multicast_group};
 use crate::core::multicast::delete_simple_multicast_group;
 use crate::{AppState, PortMapping};
 use crate::core::traffic_gen_core::event::TrafficGenEvent;
-use crate::error::P4TGError;
+use crate::error::{P4TGError, TrafficGenError};
 
 use crate::core::traffic_gen_core::const_definitions::*;
 use crate::core::traffic_gen_core::helper::{calculate_overhead, create_packet};
 use crate::core::traffic_gen_core::optimization::calculate_send_behaviour;
+use crate::core::traffic_gen_core::planner::{assign_generation_ports, combined_accuracy, egress_port_loads, generation_port_loads, resolve_rate_overrides};
 use crate::core::traffic_gen_core::types::*;
 
 /// A Traffic Generator object.
@@ -45,6 +47,8 @@ pub struct TrafficGen {
     /// Minimal buffer offset that is required for stream packets.
     /// This value is set to the size of the monitoring packets that start at position 0.
     min_buffer_offset: u32,
+    /// Generation time in ns of the monitoring packet.
+    pub monitoring_interval: u32,
     /// Indicates if the traffic generator is running.
     pub running: bool,
     /// Stored stream setting values.
@@ -62,6 +66,15 @@ pub struct TrafficGen {
     /// The port mapping indicates which ports are used for traffic generation and on which port the returning traffic
     /// is expected.
     pub port_mapping: HashMap<u32, u32>,
+    /// The traffic filters of the analyze mode are received by the REST API and stored to synchronize multiple configuration clients
+    /// (e.g., multiple open web browsers) to the same settings.
+    pub analyze_filters: Option<Vec<AnalyzeFilter>>,
+    /// The flow rules of the analyze mode are received by the REST API and stored to synchronize multiple configuration clients
+    /// (e.g., multiple open web browsers) to the same settings.
+    pub flow_rules: Option<Vec<FlowRule>>,
+    /// The sets of RX ports per TX port are received by the REST API and stored to synchronize multiple configuration clients
+    /// (e.g., multiple open web browsers) to the same settings.
+    pub port_groups: Option<HashMap<u32, Vec<u32>>>,
     /// Optional duration for the traffic generation.
     pub duration: Option<u64>, 
     /// Indicates if tofino2 is used
@@ -72,11 +85,15 @@ impl TrafficGen {
     pub fn new(is_tofino2: bool) -> TrafficGen {
         TrafficGen {
             min_buffer_offset: 0,
+            monitoring_interval: MONITORING_PACKET_INTERVAL,
             running: false,
             stream_settings: vec![],
             streams: vec![],
             mode: GenerationMode::Cbr,
             port_mapping: HashMap::new(),
+            analyze_filters: None,
+            flow_rules: None,
+            port_groups: None,
             duration: None,
             is_tofino2
         }
@@ -87,7 +104,7 @@ impl TrafficGen {
     ///
     /// * Activate the internal traffic gen capability on ports [TG_PIPE_PORTS]
     /// * Create the multicast group [MONITORING_PACKET_MID] that is mapped to all TX recirculation ports
-    /// * Create a monitoring packet and configure the internal TG to create it each [MONITORING_PACKET_INTERVAL] ns
+    /// * Create a monitoring packet and configure the internal TG to create it each `monitoring_interval` ns
     ///
     /// # Arguments
     ///
@@ -122,35 +139,8 @@ impl TrafficGen {
         // may fail if the group does not exist, therefore ignore error
         let _ = delete_simple_multicast_group(switch, MONITORING_PACKET_MID).await;
 
-        // build the monitoring packet
-        // monitoring packets are regular ethernet packets
-        // with a special ether type
-        let monitoring_packet = {
-            let pkt = etherparse::Ethernet2Header {
-                source: [0, 0, 0, 0, 0, 0], // we do not need mac src & dst
-                destination: [0, 0, 0, 0, 0, 0],
-                ether_type: 0xBB02, // Monitoring ether type
-            };
-
-            let mut result = Vec::<u8>::with_capacity(64);
-
-            pkt.write(&mut result).unwrap();
-
-            // fill with zeros
-            let padding = vec![0u8; result.capacity() - result.len()];
-            result.extend_from_slice(&padding);
-
-            result
-        };
-
         // configure send behaviour for monitoring packet
-        let res = self.configure_traffic_gen_table(switch, vec![StreamPacket {
-            app_id: 0,
-            bytes: monitoring_packet,
-            timer: MONITORING_PACKET_INTERVAL,
-            buffer_offset: Some(0),
-            n_packets: 1
-        }]).await?;
+        let res = self.configure_traffic_gen_table(switch, vec![self.monitoring_packet()]).await?;
 
         // Min buffer offset is equal to the size of the monitoring packet
         self.min_buffer_offset = res.get(&0).unwrap().bytes.len() as u32;
@@ -176,6 +166,50 @@ impl TrafficGen {
 
     }
 
+    /// Builds the monitoring packet that is generated by app 0 each `monitoring_interval` ns.
+    /// Monitoring packets are regular ethernet packets with a special ether type.
+    fn monitoring_packet(&self) -> StreamPacket {
+        let pkt = etherparse::Ethernet2Header {
+            source: [0, 0, 0, 0, 0, 0], // we do not need mac src & dst
+            destination: [0, 0, 0, 0, 0, 0],
+            ether_type: 0xBB02, // Monitoring ether type
+        };
+
+        let mut bytes = Vec::<u8>::with_capacity(64);
+
+        pkt.write(&mut bytes).unwrap();
+
+        // fill with zeros
+        let padding = vec![0u8; bytes.capacity() - bytes.len()];
+        bytes.extend_from_slice(&padding);
+
+        StreamPacket {
+            app_id: 0,
+            bytes,
+            timer: self.monitoring_interval,
+            buffer_offset: Some(0),
+            n_packets: 1
+        }
+    }
+
+    /// Changes the generation time of the monitoring packet and reprograms the timer of app 0.
+    /// This changes the granularity of the rate, packet loss and out of order statistics.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval`: Generation time in ns of the monitoring packet
+    pub async fn set_monitoring_interval(&mut self, switch: &SwitchConnection, interval: u32) -> Result<(), RBFRTError> {
+        self.monitoring_interval = interval;
+
+        let packet = self.monitoring_packet();
+
+        self.activate_traffic_gen_applications(switch, &HashMap::from([(packet.app_id, packet)])).await?;
+
+        info!("Monitoring packet interval set to {} ns.", interval);
+
+        Ok(())
+    }
+
     /// This method configures the monitoring path.
     /// It creates a mapping between different applications on all recirculation ports.
     /// This mapping is later used to monitor individual stream TX/RX rates and to remap
@@ -188,7 +222,7 @@ impl TrafficGen {
                                            switch: &SwitchConnection,
                                            port_mapping: &HashMap<u32, PortMapping>) -> Result<HashMap<u32, MonitoringMapping>, RBFRTError> {
         // first clear all related tables
-        switch.clear_tables(vec![MONITORING_INIT_TABLE, MONITORING_FORWARD_TABLE, MONITORING_EGRESS_TABLE]).await?;
+        switch.clear_tables(vec![MONITORING_INIT_TABLE, MONITORING_FORWARD_TABLE, MONITORING_EGRESS_TABLE, STREAM_INDEX_TABLE]).await?;
 
         // create a mapping between index and (port, app id)
         // used to monitor L2 rates of individual streams
@@ -285,6 +319,16 @@ impl TrafficGen {
                     .action_data("idx", *reverse_mapping.get(&(mapping.rx_recirculation, app_id)).unwrap());
 
                 egress_monitoring_requests.push(req);
+
+                // sequence number, packet loss and out of order state of the stream in ingress
+                // shares the index with the RX rate of the stream
+                let req = table::Request::new(STREAM_INDEX_TABLE)
+                    .match_key("ig_md.seq_port", MatchValue::exact(mapping.rx_recirculation))
+                    .match_key("hdr.path.app_id", MatchValue::exact(app_id))
+                    .action("ingress.p4tg.set_stream_index")
+                    .action_data("index", *reverse_mapping.get(&(mapping.rx_recirculation, app_id)).unwrap());
+
+                egress_monitoring_requests.push(req);
             }
         }
 
@@ -375,6 +419,31 @@ impl TrafficGen {
     }
 
 
+    /// Stops the traffic generation including the monitoring packets,
+    /// and removes the multicast groups of the streams and the monitoring packet.
+    /// This is used if the controller shuts down.
+    pub async fn shutdown(&mut self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
+        self.stop(switch).await?;
+
+        // app id 0 is monitoring packet
+        let update_request = Request::new(if self.is_tofino2 {APP_CFG_TF2} else {APP_CFG})
+            .match_key("app_id", MatchValue::exact(0))
+            .action("trigger_timer_periodic")
+            .action_data("app_enable", false);
+
+        switch.update_table_entry(update_request).await?;
+
+        // the multicast group id of a stream is its app id
+        // groups of unused app ids do not exist
+        for mid in 1..=MAX_STREAM_APP_ID as u16 {
+            let _ = delete_simple_multicast_group(switch, mid).await;
+        }
+
+        let _ = delete_simple_multicast_group(switch, MONITORING_PACKET_MID).await;
+
+        Ok(())
+    }
+
     /// Deactivates all traffic gen applications except for the monitoring.
     pub async fn stop(&mut self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
         self.deactivate_traffic_gen_applications(switch).await?;
@@ -429,122 +498,267 @@ impl TrafficGen {
         Ok(())
     }
 
-    /// This method is called by the REST API and completes the whole setup for the traffic generation.
+    /// Calculates the sending behaviour of the streams.
+    /// Assigns the streams to the internal traffic generation ports and adds number of packets per time,
+    /// the generation accuracy, the number of used pipes and the generation ports to the streams.
     ///
     /// # Arguments
     ///
-    /// * `state`: App state that contains various other objects that configure parts of the switch
     /// * `streams`: List of streams that should be configured
     /// * `mode`: Generation mode that should be used.
-    /// * `stream_settings`: List of stream settings that should be applied
-    /// * `tx_rx_mapping`: Mapping of TX port to expected RX port from the REST API. This is only relevant for the ANALYZE mode.
-    pub async fn start_traffic_generation(&mut self,
-                                          state: &AppState,
-                                          streams: Vec<Stream>,
-                                          mode: GenerationMode,
-                                          stream_settings: Vec<StreamSetting>,
-                                          tx_rx_mapping: &HashMap<u32, u32>) -> Result<Vec<Stream>, RBFRTError> {
-        let switch = &state.switch;
-        let port_mapping = &state.port_mapping;
+    pub fn calculate_stream_behaviour(&self, streams: Vec<Stream>, mode: &GenerationMode) -> Result<Vec<Stream>, P4TGError> {
+        let pipe_ports = if self.is_tofino2 {TG_PIPE_PORTS_TF2.to_vec()} else {TG_PIPE_PORTS.to_vec()};
+        let max_rate = if self.is_tofino2 {TG_MAX_RATE_TF2} else {TG_MAX_RATE};
 
-        // first stop possible existing generation
-        self.stop(switch).await?;
-        self.reset_tables(switch).await?;
+        // traffic rate has MPPS semantics
+        // rewrite traffic rate to reflect MPPS in Gbps
+        let streams: Vec<Stream> = streams.into_iter().map(|mut s| {
+            if *mode == GenerationMode::Mpps {
+                // preamble + inter frame gap (IFG) = 20 bytes
+                let encapsulation_overhead = calculate_overhead(&s) + 20;
 
-        // first reset all stats
-        state.frame_size_monitor.lock().await.on_reset(switch).await?;
-        state.frame_type_monitor.lock().await.on_reset(switch).await?;
-        state.rate_monitor.lock().await.on_reset(switch).await?;
-
-        // call the on_start routine on all relevant parts
-        state.frame_size_monitor.lock().await.on_start(switch, &mode).await?;
-        state.frame_type_monitor.lock().await.on_start(switch, &mode).await?;
-        state.rate_monitor.lock().await.on_start(switch, &mode).await?;
+                // recompute "correct" traffic rate in Gbps
+                s.traffic_rate = (s.frame_size + encapsulation_overhead) as f32 * 8f32 * s.traffic_rate / 1000f32;
+            }
 
-        // configure tg mode
-        self.configure_traffic_gen_mode_table(switch, &mode).await?;
+            s
+        }).collect();
 
-        // configure default forwarding
-        // this pushes rules for RX -> RX Recirc and TX Recirc -> TX
-        self.configure_default_forwarding_path(switch, &state.port_mapping).await?;
+        let assignment = if *mode == GenerationMode::Poisson {
+            // poisson traffic is generated on all pipes
+            streams.iter().map(|s| (s.app_id, pipe_ports.clone())).collect()
+        }
+        else {
+            assign_generation_ports(&streams, &pipe_ports, max_rate)?
+        };
 
-        // if rate is higher than [TWO_PIPE_GENERATION_THRESHOLD] we generate on multiple pipes
+        // if rate is higher than [TWO_PIPE_GENERATION_THRESHOLD] we generate poisson traffic on multiple pipes
         let total_rate: f32 = streams.iter().map(|x| x.traffic_rate).sum();
-        let timeout_factor: u32 = if total_rate >= TWO_PIPE_GENERATION_THRESHOLD {
-            if self.is_tofino2 {TG_PIPE_PORTS_TF2.to_vec()} else {TG_PIPE_PORTS.to_vec()}.len() as u32
-        } else { 1 };
+        let poisson_timeout_factor: u32 = if total_rate >= TWO_PIPE_GENERATION_THRESHOLD { pipe_ports.len() as u32 } else { 1 };
 
-        // calculate sending behaviour via ILP optimization
+        // calculate sending behaviour
         // further adds number of packets per time to the stream
         let mut active_streams: Vec<Stream> = streams.into_iter().map(|mut s| {
-            let encapsulation_overhead = calculate_overhead(&s);
+            let generation_ports = assignment.get(&s.app_id).cloned().unwrap_or(vec![pipe_ports[0]]);
+            let timeout_factor = if *mode == GenerationMode::Poisson { poisson_timeout_factor } else { generation_ports.len() as u32 };
 
             // preamble + inter frame gap (IFG) = 20 bytes
-            let encapsulation_overhead = encapsulation_overhead + 20;
-
-            // traffic rate has MPPS semantics
-            // rewrite traffic rate to reflect MPPS in Gbps
-            if mode == GenerationMode::Mpps {
-                // recompute "correct" traffic rate in Gbps
-                s.traffic_rate = (s.frame_size + encapsulation_overhead) as f32 * 8f32 * s.traffic_rate / 1000f32;
-            }
+            let encapsulation_overhead = calculate_overhead(&s) + 20;
 
             // call solver
-            let (n_packets, timeout) = calculate_send_behaviour(s.frame_size + encapsulation_overhead, s.traffic_rate, s.burst);
-            let rate = ((n_packets as u32) * (s.frame_size + encapsulation_overhead) * 8) as f64 / timeout as f64;
+            let (n_packets, timeout) = calculate_send_behaviour(s.frame_size + encapsulation_overhead, s.traffic_rate, s.burst, s.required_accuracy)
+                .map_err(|P4TGError::Error { message }| P4TGError::Error { message: format!("Stream #{}: {}", s.stream_id, message) })?;
+            let rate = n_packets as f64 * (s.frame_size + encapsulation_overhead) as f64 * 8f64 / timeout as f64;
             let rate_accuracy = 100f32 * (1f32 - ((s.traffic_rate - (rate as f32)).abs() / s.traffic_rate));
 
+            // packets of a burst are generated back-to-back with the maximal rate,
+            // followed by a gap until the next burst.
+            // the IATs deviate at most (n - 1) * (mean IAT - back-to-back IAT) from their mean
+            let back_to_back_iat = (s.frame_size + encapsulation_overhead) as f32 * 8f32 / max_rate;
+            let iat_jitter = ((n_packets - 1) as f32 * (timeout as f32 / n_packets as f32 - back_to_back_iat)).max(0f32);
+
             info!("Calculated traffic generation for stream #{}. #{} packets per {} ns. Rate: {} Gbps. Accuracy: {:.2}%.", s.app_id, n_packets, timeout, rate, rate_accuracy);
 
             // add calculated values to the stream
             s.n_packets = Some(n_packets);
             s.timeout = Some(timeout * timeout_factor);
             s.generation_accuracy = Some(rate_accuracy);
+            s.iat_jitter = Some(iat_jitter);
             s.n_pipes = Some(timeout_factor as u8);
+            s.generation_ports = Some(generation_ports);
 
-            s
-        }).collect();
+            Ok(s)
+        }).collect::<Result<_, P4TGError>>()?;
 
         // poisson mode
         // send with full capacity and then randomly drop in data plane to get geometric IAT distribution
-        if mode == GenerationMode::Poisson {
+        if *mode == GenerationMode::Poisson {
             // send with full capacity
             let stream = active_streams.get_mut(0).ok_or(P4TGError::Error {message: "Configuration error.".to_owned()})?;
             let encap_overhead = 20 + calculate_overhead(stream);
 
-            let (n_packets, timeout) = calculate_send_behaviour(stream.frame_size + encap_overhead, if self.is_tofino2 {TG_MAX_RATE_TF2} else {TG_MAX_RATE}, 25);
-            active_streams.get_mut(0).ok_or(P4TGError::Error {message: "Configuration error.".to_owned()})?.n_packets = Some(n_packets);
-            active_streams.get_mut(0).ok_or(P4TGError::Error {message: "Configuration error.".to_owned()})?.timeout = Some(timeout * timeout_factor);
+            let (n_packets, timeout) = calculate_send_behaviour(stream.frame_size + encap_overhead, max_rate, 25, None)?;
+            stream.n_packets = Some(n_packets);
+            stream.timeout = Some(timeout * poisson_timeout_factor);
+            // IATs are random
+            stream.iat_jitter = None;
         }
 
-        // calculate the required multicast ports for a stream
-        // this mapping will contain StreamId -> Set of egress ports
-        let mut stream_to_ports: HashMap<u8, HashSet<u32>> = HashMap::new();
+        Ok(active_streams)
+    }
 
-        for stream in &stream_settings {
-            let out_port = port_mapping.get(&stream.port).unwrap().tx_recirculation;
-            stream_to_ports.entry(stream.stream_id).or_default().insert(out_port);
+    /// Calculates the multicast groups that replicate the streams to their egress ports.
+    /// Returns a mapping of multicast group id (= app id) to the TX recirculation ports.
+    ///
+    /// # Arguments
+    ///
+    /// * `streams`: List of streams that should be configured
+    /// * `stream_settings`: List of stream settings that should be applied
+    /// * `port_mapping`: Mapping of front panel port to TX / RX recirculation port
+    fn calculate_multicast_groups(&self, streams: &[Stream], stream_settings: &[StreamSetting], port_mapping: &HashMap<u32, PortMapping>) -> Result<BTreeMap<u16, Vec<u32>>, P4TGError> {
+        let mut groups = BTreeMap::new();
+
+        for stream in streams {
+            // calculate the required multicast ports for a stream
+            let mut ports = vec![];
+
+            for setting in stream_settings.iter().filter(|setting| setting.belongs_to(stream)) {
+                let out_port = port_mapping.get(&setting.port).ok_or(P4TGError::Error { message: format!("Port {} in stream settings does not exist on device.", setting.port)})?.tx_recirculation;
+                ports.push(out_port);
+            }
+
+            if ports.is_empty() {
+                return Err(P4TGError::Error { message: format!("No stream setting for stream #{}.", stream.stream_id)});
+            }
+
+            ports.sort();
+            ports.dedup();
+
+            groups.insert(stream.app_id as u16, ports);
         }
 
-        // delete and create simple multicast group for each stream
-        for stream in &active_streams {
-            let _ = delete_simple_multicast_group(switch, stream.app_id as u16).await;
-            let ports = stream_to_ports.get(&stream.stream_id).unwrap().clone().into_iter().collect::<Vec<_>>();
+        Ok(groups)
+    }
 
-            create_simple_multicast_group(switch, stream.app_id as u16, &ports).await?;
+    /// Creates a plan of the traffic generation without configuring the switch.
+    /// The plan contains the calculated sending behaviour, the load of the generation and egress ports,
+    /// the packet buffer layout, the multicast groups and the header rewrite entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `port_mapping`: Mapping of front panel port to TX / RX recirculation port
+    /// * `port_speeds`: Speed in Gbps of the front panel ports
+    /// * `streams`: List of streams that should be configured
+    /// * `mode`: Generation mode that should be used.
+    /// * `stream_settings`: List of stream settings that should be applied
+    pub fn plan_traffic_generation(&self,
+                                   port_mapping: &HashMap<u32, PortMapping>,
+                                   port_speeds: &HashMap<u32, f32>,
+                                   streams: Vec<Stream>,
+                                   mode: GenerationMode,
+                                   stream_settings: &[StreamSetting]) -> Result<TrafficGenPlan, P4TGError> {
+        let (streams, stream_settings) = resolve_rate_overrides(streams, stream_settings.to_vec())?;
+        let stream_settings = &stream_settings;
+
+        let active_streams = self.calculate_stream_behaviour(streams, &mode)?;
+        let multicast_groups = self.calculate_multicast_groups(&active_streams, stream_settings, port_mapping)?;
+
+        // no traffic is generated in analyze mode
+        if mode == GenerationMode::Analyze {
+            return Ok(TrafficGenPlan { streams: active_streams, multicast_groups, ..Default::default() });
         }
 
-        let packet_bytes: Vec<StreamPacket> = active_streams.iter().map(|s| {
+        let pipe_ports = if self.is_tofino2 {TG_PIPE_PORTS_TF2.to_vec()} else {TG_PIPE_PORTS.to_vec()};
+        let egress_ports = egress_port_loads(&active_streams, stream_settings, port_speeds);
+
+        let mut packet_buffer: Vec<PacketBufferEntry> = self.calculate_buffer_layout(self.create_stream_packets(&active_streams))
+            .into_values()
+            .map(|p| PacketBufferEntry {
+                app_id: p.app_id,
+                buffer_offset: p.buffer_offset.unwrap(),
+                size: p.bytes.len() as u32,
+                n_packets: p.n_packets,
+                timer: p.timer
+            })
+            .collect();
+        packet_buffer.sort_by_key(|p| p.buffer_offset);
+
+        let header_rewrites = self.create_packet_header_rewrite_requests(&active_streams, stream_settings, port_mapping)?
+            .into_iter()
+            .map(|(entry, _)| entry)
+            .collect();
+
+        Ok(TrafficGenPlan {
+            generation_accuracy: combined_accuracy(&active_streams),
+            generation_ports: generation_port_loads(&active_streams, &pipe_ports),
+            errors: vec![],
+            egress_ports,
+            streams: active_streams,
+            packet_buffer,
+            multicast_groups,
+            header_rewrites
+        })
+    }
+
+    /// Creates the byte representation of the stream packets.
+    fn create_stream_packets(&self, streams: &[Stream]) -> Vec<StreamPacket> {
+        streams.iter().map(|s| {
             let packet = create_packet(s);
             StreamPacket { app_id: s.app_id, bytes: packet, buffer_offset: None, timer: s.timeout.unwrap(), n_packets: s.n_packets.unwrap() }
-        }).collect();
+        }).collect()
+    }
+
+    /// This method is called by the REST API and completes the whole setup for the traffic generation.
+    /// Returns the configured streams without the internal copies for rate overrides,
+    /// or [TrafficGenError::Invalid] if the requested traffic can not be generated. The switch is not touched in that case.
+    ///
+    /// # Arguments
+    ///
+    /// * `state`: App state that contains various other objects that configure parts of the switch
+    /// * `streams`: List of streams that should be configured
+    /// * `mode`: Generation mode that should be used.
+    /// * `stream_settings`: List of stream settings that should be applied
+    /// * `tx_rx_mapping`: Mapping of TX port to the set of expected RX ports from the REST API.
+    /// * `analyze_filters`: Traffic filters for the ANALYZE mode. Only matching frames are counted.
+    /// * `flow_rules`: Flow classification rules for the ANALYZE mode.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start_traffic_generation(&mut self,
+                                          state: &AppState,
+                                          streams: Vec<Stream>,
+                                          mode: GenerationMode,
+                                          stream_settings: Vec<StreamSetting>,
+                                          tx_rx_mapping: &HashMap<u32, Vec<u32>>,
+                                          analyze_filters: &[AnalyzeFilter],
+                                          flow_rules: &[FlowRule]) -> Result<Vec<Stream>, TrafficGenError> {
+        let switch = &*state.switch.read().await;
+        let port_mapping = &state.port_mapping;
+
+        // calculate the sending behaviour and the multicast groups
+        // before the switch is touched
+        let (streams, stream_settings) = resolve_rate_overrides(streams, stream_settings)?;
+        let active_streams = self.calculate_stream_behaviour(streams, &mode)?;
+        let multicast_groups = self.calculate_multicast_groups(&active_streams, &stream_settings, port_mapping)?;
+
+        // first stop possible existing generation
+        self.stop(switch).await?;
+        self.reset_tables(switch).await?;
+
+        state.flow_monitor.lock().await.set_rules(flow_rules.to_vec(), tx_rx_mapping);
+        state.rate_monitor.lock().await.set_rx_port_groups(tx_rx_mapping);
+        state.rate_monitor.lock().await.set_streams(&active_streams, &stream_settings, tx_rx_mapping);
+
+        // first reset all stats
+        state.frame_size_monitor.lock().await.on_reset(switch).await?;
+        state.frame_type_monitor.lock().await.on_reset(switch).await?;
+        state.flow_monitor.lock().await.on_reset(switch).await?;
+        state.rate_monitor.lock().await.on_reset(switch).await?;
+
+        // call the on_start routine on all relevant parts
+        state.frame_size_monitor.lock().await.on_start(switch, &mode).await?;
+        state.frame_type_monitor.lock().await.on_start(switch, &mode).await?;
+        state.flow_monitor.lock().await.on_start(switch, &mode).await?;
+        state.rate_monitor.lock().await.on_start(switch, &mode).await?;
+
+        // configure tg mode
+        self.configure_traffic_gen_mode_table(switch, &mode).await?;
+
+        // configure default forwarding
+        // this pushes rules for RX -> RX Recirc and TX Recirc -> TX
+        self.configure_default_forwarding_path(switch, &state.port_mapping).await?;
+
+        // delete and create simple multicast group for each stream
+        for (mid, ports) in &multicast_groups {
+            let _ = delete_simple_multicast_group(switch, *mid).await;
+
+            create_simple_multicast_group(switch, *mid, ports).await?;
+        }
 
         // configure egress table rules
         // we dont want to rewrite tx seq and timestamp of potential
         // other P4TG traffic when we are in analyze mode
         if mode != GenerationMode::Analyze {
             // write packet content to traffic gen table
-            let packet_mapping: HashMap<u8, StreamPacket> = self.configure_traffic_gen_table(switch, packet_bytes.clone()).await?;
+            let packet_mapping: HashMap<u8, StreamPacket> = self.configure_traffic_gen_table(switch, self.create_stream_packets(&active_streams)).await?;
 
             // write forwarding entries for newly generated stream traffic
             self.configure_traffic_gen_forwarding_table(switch, &active_streams, mode).await?;
@@ -558,11 +772,16 @@ impl TrafficGen {
             // configure analyze forwarding rules
             // this installs the rules RX recirc -> TX recirc s.t. packets are forwarded
             self.configure_analyze_forwarding(switch, port_mapping, tx_rx_mapping).await?;
+            self.configure_analyze_filters(switch, tx_rx_mapping, analyze_filters).await?;
         }
 
         self.running = true;
 
-        Ok(active_streams)
+        // copies of the streams for rate overrides are internal
+        // the stream itself comes first, see [resolve_rate_overrides]
+        let mut stream_ids = HashSet::new();
+
+        Ok(active_streams.into_iter().filter(|s| stream_ids.insert(s.stream_id)).collect())
     }
 
 
@@ -571,12 +790,12 @@ impl TrafficGen {
     ///
     /// # Arguments
     ///
-    /// * `tx_rx_mapping`: Mapping of TX port to expected RX port from the REST API. This is only relevant for the ANALYZE mode.
+    /// * `tx_rx_mapping`: Mapping of TX port to the set of expected RX ports from the REST API.
     /// * `port_mapping`: Mapping of front panel port to TX / RX recirculation port
-    async fn configure_analyze_forwarding(&self, switch: &SwitchConnection, port_mapping: &HashMap<u32, PortMapping>, tx_rx_mapping: &HashMap<u32, u32>) -> Result<(), RBFRTError> {
+    async fn configure_analyze_forwarding(&self, switch: &SwitchConnection, port_mapping: &HashMap<u32, PortMapping>, tx_rx_mapping: &HashMap<u32, Vec<u32>>) -> Result<(), RBFRTError> {
         let mut reqs = vec![];
 
-        for (tx, rx) in tx_rx_mapping {
+        for (tx, rx) in tx_rx_mapping.iter().flat_map(|(tx, rx_ports)| rx_ports.iter().map(move |rx| (tx, rx))) {
             let rx_recirc = port_mapping.get(rx).ok_or(P4TGError::Error {message: "Incorrect configuration.".to_owned()})?.rx_recirculation;
             let tx_recirc = port_mapping.get(tx).ok_or(P4TGError::Error {message: "Incorrect configuration.".to_owned()})?.tx_recirculation;
 
@@ -594,6 +813,78 @@ impl TrafficGen {
         Ok(())
     }
 
+    /// This method configures the traffic filters in the case of [GenerationMode::Analyze].
+    /// Frames received on an RX port that match a filter take the measurement path through the recirculation ports.
+    /// All other frames bypass the recirculation ports and are forwarded directly to the TX port,
+    /// i.e., they are not counted in the statistics.
+    /// No filter rules are installed if no filters are given.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_rx_mapping`: Mapping of TX port to the set of expected RX ports from the REST API.
+    /// * `analyze_filters`: Traffic filters from the REST API.
+    async fn configure_analyze_filters(&self, switch: &SwitchConnection, tx_rx_mapping: &HashMap<u32, Vec<u32>>, analyze_filters: &[AnalyzeFilter]) -> Result<(), RBFRTError> {
+        if analyze_filters.is_empty() {
+            return Ok(());
+        }
+
+        let mut reqs = vec![];
+
+        for (tx, rx) in tx_rx_mapping.iter().flat_map(|(tx, rx_ports)| rx_ports.iter().map(move |rx| (tx, rx))) {
+            // lower value, higher priority
+            for filter in analyze_filters.iter().filter(|f| f.port.is_none() || f.port == Some(*rx)) {
+                let (vlan_valid, vlan_id) = match filter.vlan_id {
+                    Some(id) => (MatchValue::ternary(1u8, 1u8), MatchValue::ternary(id, 0xFFF)),
+                    None => (MatchValue::ternary(0u8, 0u8), MatchValue::ternary(0u16, 0u16))
+                };
+
+                let ip_required = filter.ip_src.is_some() || filter.ip_dst.is_some() || filter.dscp.is_some();
+
+                let prefix = |ip: Option<Ipv4Addr>, length: Option<u8>| match ip {
+                    Some(ip) => {
+                        let mask = u32::MAX.checked_shl(32 - length.unwrap_or(32).min(32) as u32).unwrap_or(0);
+                        MatchValue::ternary(u32::from(ip) & mask, mask)
+                    }
+                    None => MatchValue::ternary(0u32, 0u32)
+                };
+
+                let dscp = match filter.dscp {
+                    // DSCP are the upper 6 bits of the diffserv field
+                    Some(dscp) => MatchValue::ternary(dscp << 2, 0xFCu8),
+                    None => MatchValue::ternary(0u8, 0u8)
+                };
+
+                reqs.push(table::Request::new(ANALYZE_FILTER_TABLE)
+                    .match_key("ig_intr_md.ingress_port", MatchValue::exact(*rx))
+                    .match_key("hdr.vlan.$valid", vlan_valid)
+                    .match_key("hdr.vlan.vid", vlan_id)
+                    .match_key("hdr.inner_ipv4.$valid", if ip_required {MatchValue::ternary(1u8, 1u8)} else {MatchValue::ternary(0u8, 0u8)})
+                    .match_key("hdr.inner_ipv4.src_addr", prefix(filter.ip_src, filter.ip_src_prefix))
+                    .match_key("hdr.inner_ipv4.dst_addr", prefix(filter.ip_dst, filter.ip_dst_prefix))
+                    .match_key("hdr.inner_ipv4.diffserv", dscp)
+                    .match_key("$MATCH_PRIORITY", MatchValue::exact(1))
+                    .action("ingress.p4tg.nop"));
+            }
+
+            // all other frames bypass the measurement path
+            reqs.push(table::Request::new(ANALYZE_FILTER_TABLE)
+                .match_key("ig_intr_md.ingress_port", MatchValue::exact(*rx))
+                .match_key("hdr.vlan.$valid", MatchValue::ternary(0u8, 0u8))
+                .match_key("hdr.vlan.vid", MatchValue::ternary(0u16, 0u16))
+                .match_key("hdr.inner_ipv4.$valid", MatchValue::ternary(0u8, 0u8))
+                .match_key("hdr.inner_ipv4.src_addr", MatchValue::ternary(0u32, 0u32))
+                .match_key("hdr.inner_ipv4.dst_addr", MatchValue::ternary(0u32, 0u32))
+                .match_key("hdr.inner_ipv4.diffserv", MatchValue::ternary(0u8, 0u8))
+                .match_key("$MATCH_PRIORITY", MatchValue::exact(2))
+                .action("ingress.p4tg.bypass")
+                .action_data("e_port", *tx));
+        }
+
+        switch.write_table_entries(reqs).await?;
+
+        Ok(())
+    }
+
     /// Configures the forwarding table for generated traffic.
     /// For [GenerationMode::Poisson], it also calculates the drop probability.
     async fn configure_traffic_gen_forwarding_table(&self, switch: &SwitchConnection, streams: &Vec<Stream>, mode: GenerationMode) -> Result<(), RBFRTError> {
@@ -602,16 +893,10 @@ impl TrafficGen {
 
         let mut forward_entries = vec![];
 
-        let overall_traffic_rate: f32 = streams.iter().map(|x| x.traffic_rate).sum();
-
-        // we generate on both pipes if the overall rate is larger than the threshold or if we do poisson traffic
-        let generation_ports = if overall_traffic_rate < TWO_PIPE_GENERATION_THRESHOLD && mode != GenerationMode::Poisson {
-            vec![if self.is_tofino2 {TG_PIPE_PORTS_TF2[0]} else {TG_PIPE_PORTS[0]}]
-        }
-        else if self.is_tofino2 {TG_PIPE_PORTS_TF2.to_vec()} else {TG_PIPE_PORTS.to_vec()};
-
         for s in streams {
-            for port in &generation_ports {
+            // packets of a stream are only forwarded from its assigned generation ports
+            // and dropped on all other pipes
+            for port in s.generation_ports.iter().flatten() {
                 let rand_value = {
                     // compute drop probability for poisson traffic
                     if mode != GenerationMode::Poisson { // no poisson, dont drop
@@ -650,12 +935,29 @@ impl TrafficGen {
     /// * `streams`: List of streams that should be configured
     /// * `stream_settings`: List of stream settings that should be applied
     /// * `port_mapping`: Mapping of front panel port to TX / RX recirculation port
-    async fn configure_packet_header_rewrite(&self, switch: &SwitchConnection, streams: &Vec<Stream>, stream_settings: &Vec<StreamSetting>, port_mapping: &HashMap<u32, PortMapping>) -> Result<(), RBFRTError> {
+    async fn configure_packet_header_rewrite(&self, switch: &SwitchConnection, streams: &[Stream], stream_settings: &[StreamSetting], port_mapping: &HashMap<u32, PortMapping>) -> Result<(), RBFRTError> {
+        let reqs = self.create_packet_header_rewrite_requests(streams, stream_settings, port_mapping)?
+            .into_iter()
+            .map(|(_, req)| req)
+            .collect();
+
+        info!("Configure table {}, {}, & {}.", ETHERNET_IP_HEADER_REPLACE_TABLE, VLAN_HEADER_REPLACE_TABLE, MPLS_HEADER_REPLACE_TABLE);
+        switch.write_table_entries(reqs).await?;
+
+        Ok(())
+    }
+
+    /// Creates the table entries that rewrite the packet headers.
+    /// Each request is returned together with a description of the entry.
+    /// * `streams`: List of streams that should be configured
+    /// * `stream_settings`: List of stream settings that should be applied
+    /// * `port_mapping`: Mapping of front panel port to TX / RX recirculation port
+    fn create_packet_header_rewrite_requests(&self, streams: &[Stream], stream_settings: &[StreamSetting], port_mapping: &HashMap<u32, PortMapping>) -> Result<Vec<(HeaderRewriteEntry, Request)>, P4TGError> {
         let mut reqs = vec![];
 
         for s in streams {
             for setting in stream_settings { // find the "correct" stream for a stream setting
-                if setting.stream_id != s.stream_id || !setting.active {
+                if !setting.belongs_to(s) || !setting.active {
                     continue;
                 }
 
@@ -663,6 +965,14 @@ impl TrafficGen {
                 let src_mac = MacAddr::from_str(&setting.ethernet.eth_src).map_err(|_| P4TGError::Error { message: String::from("Source mac in stream settings not valid.")})?;
                 let dst_mac = MacAddr::from_str(&setting.ethernet.eth_dst).map_err(|_| P4TGError::Error { message: String::from("Destination mac in stream settings not valid.")})?;
 
+                let entry = |table: &str, action: &str| HeaderRewriteEntry {
+                    table: table.to_owned(),
+                    action: action.to_owned(),
+                    port: setting.port,
+                    egress_port: port.tx_recirculation,
+                    app_id: s.app_id
+                };
+
                 let req = if s.vxlan { // we need to rewrite two Ethernet & IP headers
                     // validation method in API makes sure that setting.vxlan exists if s.vxlan is set
                     let vxlan = setting.vxlan.as_ref().unwrap();
@@ -702,7 +1012,8 @@ impl TrafficGen {
                         .action_data("tos", setting.ip.ip_tos)
                 };
 
-                reqs.push(req);
+                let action = if s.vxlan {"egress.header_replace.rewrite_vxlan"} else {"egress.header_replace.rewrite"};
+                reqs.push((entry(ETHERNET_IP_HEADER_REPLACE_TABLE, action), req));
 
                 if s.encapsulation == Encapsulation::QinQ {
                     // we checked in validation that vlan exists
@@ -719,7 +1030,7 @@ impl TrafficGen {
                         .action_data("inner_dei", vlan.inner_dei)
                         .action_data("inner_vlan_id", vlan.inner_vlan_id);
 
-                    reqs.push(req);
+                    reqs.push((entry(VLAN_HEADER_REPLACE_TABLE, "egress.header_replace.rewrite_q_in_q"), req));
                 }
                 else if s.encapsulation == Encapsulation::Vlan {
                     // we checked in validation that vlan exists
@@ -733,7 +1044,7 @@ impl TrafficGen {
                         .action_data("dei", vlan.dei)
                         .action_data("vlan_id", vlan.vlan_id);
 
-                    reqs.push(req);
+                    reqs.push((entry(VLAN_HEADER_REPLACE_TABLE, "egress.header_replace.rewrite_vlan"), req));
                 }
                 else if s.encapsulation == Encapsulation::Mpls {
                     // we checked that mpls stack exists
@@ -757,15 +1068,12 @@ impl TrafficGen {
                                 .action_data(&tc_param, lse.tc);
                     }
 
-                    reqs.push(req.clone());
+                    reqs.push((entry(MPLS_HEADER_REPLACE_TABLE, &action_name), req));
                 }
             }
         }
 
-        info!("Configure table {}, {}, & {}.", ETHERNET_IP_HEADER_REPLACE_TABLE, VLAN_HEADER_REPLACE_TABLE, MPLS_HEADER_REPLACE_TABLE);
-        switch.write_table_entries(reqs).await?;
-
-        Ok(())
+        Ok(reqs)
     }
 
     /// Stores the byte representation of the packets in the Tofino internal table
@@ -775,7 +1083,27 @@ impl TrafficGen {
     ///
     /// * `packets`: List of packets that should be configured.
     async fn configure_traffic_gen_table(&self, switch: &SwitchConnection, packets: Vec<StreamPacket>) -> Result<HashMap<u8, StreamPacket>, RBFRTError> {
-        let mut requests = vec![];
+        let app_to_offset = self.calculate_buffer_layout(packets);
+
+        let requests = app_to_offset.values().map(|p| {
+            table::Request::new(if self.is_tofino2 {APP_BUFFER_CFG_TF2} else {APP_BUFFER_CFG})
+                .match_key("pkt_buffer_offset", MatchValue::exact(p.buffer_offset.unwrap()))
+                .match_key("pkt_buffer_size", MatchValue::exact(p.bytes.len() as u32))
+                .action_data_repeated("buffer", vec![p.bytes.to_vec()])
+        }).collect();
+
+        switch.update_table_entries(requests).await?;
+
+        Ok(app_to_offset)
+    }
+
+    /// Calculates the offsets of the packets in the Tofino internal table.
+    /// Returns a mapping of app_id to packet with set buffer offset.
+    ///
+    /// # Arguments
+    ///
+    /// * `packets`: List of packets that should be configured.
+    fn calculate_buffer_layout(&self, packets: Vec<StreamPacket>) -> HashMap<u8, StreamPacket> {
         let mut buffer_offset = self.min_buffer_offset;
 
         let mut app_to_offset = HashMap::new();
@@ -788,23 +1116,14 @@ impl TrafficGen {
                 buffer_offset += 16 - (buffer_offset % 16);
             }
 
-            let req = table::Request::new(if self.is_tofino2 {APP_BUFFER_CFG_TF2} else {APP_BUFFER_CFG})
-                .match_key("pkt_buffer_offset", MatchValue::exact(buffer_offset))
-                .match_key("pkt_buffer_size", MatchValue::exact(pkt_len))
-                .action_data_repeated("buffer", vec![p.bytes.to_vec()]);
-
             p.buffer_offset = Some(buffer_offset);
 
             app_to_offset.insert(p.app_id, p);
 
             buffer_offset += pkt_len;
-
-            requests.push(req);
         }
 
-        switch.update_table_entries(requests).await?;
-
-        Ok(app_to_offset)
+        app_to_offset
     }
 
     /// Configures the traffic gen mode table in the data plane
@@ -822,7 +1141,7 @@ impl TrafficGen {
 
     /// Clears various tables that are refilled during traffic gen setup
     async fn reset_tables(&self, switch: &SwitchConnection) -> Result<(), RBFRTError> {
-        switch.clear_tables(vec![TRAFFIC_GEN_MODE, IS_EGRESS_TABLE, IS_TX_EGRESS_TABLE, VLAN_HEADER_REPLACE_TABLE, MPLS_HEADER_REPLACE_TABLE,  ETHERNET_IP_HEADER_REPLACE_TABLE, DEFAULT_FORWARD_TABLE]).await?;
+        switch.clear_tables(vec![TRAFFIC_GEN_MODE, IS_EGRESS_TABLE, IS_TX_EGRESS_TABLE, VLAN_HEADER_REPLACE_TABLE, MPLS_HEADER_REPLACE_TABLE,  ETHERNET_IP_HEADER_REPLACE_TABLE, DEFAULT_FORWARD_TABLE, ANALYZE_FILTER_TABLE]).await?;
 
         Ok(())
     }
@@ -36,14 +36,42 @@ pub const P4TG_SOURCE_PORT: u16 = 50081;
 /// This is used in the data plane to detect P4TG traffic.
 pub const P4TG_DST_PORT: u16 = 50083;
 
-/// Max time for the ILP solver that calculates the sending behaviour.
-/// See [this method](TrafficGen::calculate_send_behaviour) for the solver.
-pub const SOLVER_TIME_LIMIT_IN_SECONDS: f64 = 2f64;
-
-/// Generation time in ns for the monitoring packet.
+/// Default generation time in ns for the monitoring packet.
 /// Each [MONITORING_PACKET_INTERVAL] ns, a monitoring packet is created.
 pub const MONITORING_PACKET_INTERVAL: u32 = 500000000;
 
+/// Minimal generation time in ns for the monitoring packet that can be configured.
+/// Each monitoring packet results in a digest per recirculation port.
+pub const MIN_MONITORING_PACKET_INTERVAL: u32 = 10000000;
+
+/// Maximal generation time in ns for the monitoring packet that can be configured.
+pub const MAX_MONITORING_PACKET_INTERVAL: u32 = 4000000000;
+
+/// Default number of RTT / IAT samples per second and recirculation port.
+pub const DEFAULT_SAMPLE_RATE: u32 = 800;
+
+/// Maximal number of RTT / IAT samples per second and recirculation port that can be configured.
+pub const MAX_SAMPLE_RATE: u32 = 10000;
+
+/// Default percentiles of the RTT that are reported in the statistics.
+pub const DEFAULT_RTT_PERCENTILES: [f64; 4] = [50.0, 90.0, 99.0, 99.9];
+
+/// Maximal number of buckets of the RTT histogram.
+pub const MAX_HISTOGRAM_BINS: u32 = 1000;
+
+/// Percentile of the RTT that is used for the packet delay variation (RFC 5481).
+pub const PDV_PERCENTILE: f64 = 99.9;
+
+/// Maximal number of loss events that are logged per traffic generation.
+pub const MAX_LOSS_EVENTS: usize = 10000;
+
+/// Interval in seconds between reconnection attempts if the connection to the switch is lost.
+pub const RECONNECT_INTERVAL: u64 = 5;
+
+/// Time in seconds without digests after which the connection to the switch is checked.
+/// Monitoring packets are generated at least every 4.3 s (monitoring interval in ns as u32).
+pub const DIGEST_TIMEOUT: u64 = 10;
+
 /// Multicast group ID for monitoring packet
 pub const MONITORING_PACKET_MID: u16 = 1000;
 
@@ -58,6 +86,17 @@ pub const MONITORING_FORWARD_TABLE: &str = "ingress.p4tg.monitor_forward";
 /// Triggers monitoring in egress for individual streams
 pub const MONITORING_EGRESS_TABLE: &str = "egress.monitor_stream";
 
+/// Maps received P4TG traffic in ingress to the index of its (RX port, app id).
+/// The index is used for the sequence number, packet loss and out of order state of individual streams.
+pub const STREAM_INDEX_TABLE: &str = "ingress.p4tg.stream_index";
+
+/// Maps the reordering extent of a late packet to its bucket of the reorder density.
+pub const REORDER_EXTENT_TABLE: &str = "ingress.p4tg.reorder_extent";
+
+/// Buckets (lower, upper) of the reordering extent that are used for the reorder density (RFC 5236).
+/// Must match the number of buckets per stream in the `reorder_density` register in the data plane.
+pub const REORDER_EXTENT_BUCKETS: [(u32, u32); 8] = [(0, 0), (1, 1), (2, 2), (3, 4), (5, 8), (9, 16), (17, 32), (33, u32::MAX)];
+
 /// Indicates the current generation type
 pub const TRAFFIC_GEN_MODE: &str = "ingress.tg_mode";
 
@@ -73,6 +112,10 @@ pub const IS_TX_EGRESS_TABLE: &str = "egress.is_tx_recirc";
 /// Table that contains the default forwarding from port to RX recirulation port
 pub const DEFAULT_FORWARD_TABLE: &str = "ingress.p4tg.forward";
 
+/// Table that filters the received traffic in analyze mode.
+/// Frames that do not match a filter bypass the measurement path.
+pub const ANALYZE_FILTER_TABLE: &str = "ingress.p4tg.analyze_filter";
+
 /// Table that contains the forwarding / multicast entries for generated stream traffic
 pub const STREAM_FORWARD_TABLE: &str = "ingress.p4tg.tg_forward";
 
@@ -86,7 +129,24 @@ pub const TG_MAX_RATE: f32 = 100f32;
 /// Maximal traffic rate on tofino2 per port
 pub const TG_MAX_RATE_TF2: f32 = 400f32;
 
-/// Threshold in Gbps to use both generation pipes
+/// Maximal number of traffic generation ports (including breakout channels).
+/// Limited by the `forward` table of the ingress (P4TG_Ingress.p4, size 64),
+/// which requires two entries per port (front panel port to RX recirculation port and TX recirculation port to front panel port).
+pub const MAX_TG_PORTS: usize = 32;
+
+/// Largest front panel port that is considered for traffic generation and recirculation.
+pub const MAX_FRONT_PANEL_PORT: u32 = 64;
+
+/// Largest app id of the traffic generator that can be used for streams.
+/// App id 0 is used for the monitoring packet.
+pub const MAX_STREAM_APP_ID: u8 = 7;
+
+/// Generation accuracy in percent that is targeted if a stream does not require an accuracy.
+/// The smallest burst that reaches this accuracy is used.
+pub const DEFAULT_GENERATION_ACCURACY: f32 = 99.9;
+
+/// Rate in Gbps that a single generation pipe generates accurately.
+/// Above this load, streams are split across all generation pipes.
 pub const TWO_PIPE_GENERATION_THRESHOLD: f32 = 75.0;
 
 /// Ethernet & IP header replace table
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use etherparse::PacketBuilder;
 use crate::core::traffic_gen_core::const_definitions::{P4TG_DST_PORT, P4TG_SOURCE_PORT, VX_LAN_UDP_PORT};
 use crate::core::traffic_gen_core::types::*;
+use rbfrt::util::port_manager::Speed;
 
 pub(crate) fn calculate_overhead(stream: &Stream) -> u32 {
     let mut encapsulation_overhead = match stream.encapsulation {
@@ -223,3 +225,33 @@ pub(crate) fn create_packet(s: &Stream) -> Vec<u8> {
         }
     }
 }
+
+/// Returns the speed of a port in Gbps
+pub(crate) fn speed_to_gbps(speed: &Speed) -> f32 {
+    match speed {
+        Speed::BF_SPEED_1G => 1.0,
+        Speed::BF_SPEED_10G => 10.0,
+        Speed::BF_SPEED_20G => 20.0,
+        Speed::BF_SPEED_25G => 25.0,
+        Speed::BF_SPEED_40G => 40.0,
+        Speed::BF_SPEED_50G => 50.0,
+        Speed::BF_SPEED_100G => 100.0,
+        Speed::BF_SPEED_400G => 400.0,
+    }
+}
+
+/// Merges the mapping of TX port to RX port and the optional sets of RX ports per TX port.
+/// A set of RX ports replaces the RX port of the mapping for the same TX port.
+pub(crate) fn rx_port_groups(tx_rx_mapping: &HashMap<u32, u32>, tx_rx_groups: Option<&HashMap<u32, Vec<u32>>>) -> HashMap<u32, Vec<u32>> {
+    let mut groups: HashMap<u32, Vec<u32>> = tx_rx_mapping.iter().map(|(tx, rx)| (*tx, vec![*rx])).collect();
+
+    for (tx, rx_ports) in tx_rx_groups.into_iter().flatten() {
+        let mut rx_ports = rx_ports.clone();
+        rx_ports.sort();
+        rx_ports.dedup();
+
+        groups.insert(*tx, rx_ports);
+    }
+
+    groups
+}
@@ -20,4 +20,6 @@ pub mod event;
 pub mod const_definitions;
 pub mod types;
 pub mod optimization;
-pub mod helper;
\ No newline at end of file
+pub mod planner;
+pub mod helper;#[cfg(test)]
+pub mod test_util;
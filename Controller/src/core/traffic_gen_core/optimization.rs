@@ -17,54 +17,214 @@
  * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
  */
 
-use highs::{HighsModelStatus, Sense};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
 use log::warn;
-use crate::core::traffic_gen_core::const_definitions::SOLVER_TIME_LIMIT_IN_SECONDS;
+use crate::core::traffic_gen_core::const_definitions::DEFAULT_GENERATION_ACCURACY;
+use crate::error::P4TGError;
+
+/// Key of the sending behaviour cache: (frame size, traffic rate bits, max burst, required accuracy bits)
+type SendBehaviourKey = (u32, u32, u16, Option<u32>);
+
+/// Maximal number of cached sending behaviours.
+/// The cache is cleared if it is full, e.g., after many distinct rates of RFC 2544 binary searches.
+const SEND_BEHAVIOUR_CACHE_SIZE: usize = 1024;
+
+lazy_static! {
+    /// Caches the calculated sending behaviour.
+    /// RFC 2544 tests repeatedly request the same configurations.
+    static ref SEND_BEHAVIOUR_CACHE: Mutex<HashMap<SendBehaviourKey, (u16, u32)>> = Mutex::new(HashMap::new());
+}
 
 /// Calculates the number of packets (n) that should be sent per timeout ns limited by `max_burst`.
-/// Optimized for traffic rate accuracy and burst minimization.
-/// Uses highs ILP solver.
 ///
-/// It solves the ILP
+/// The inter-arrival time `frame_size * 8 / traffic_rate` is approximated by the fraction `timeout / n`
+/// with `n` bounded by the maximal burst. For each `n`, the closest integer timeout is used that
+/// generates at most one bit more per timeout than requested, as in the previously used ILP.
+/// The calculation is deterministic.
 ///
-/// min c + n
-/// s.t.
-/// 0 <= c - timeout * `traffic_rate` + (n * `frame_size` * 8) <= 0
+/// The smallest burst that reaches `required_accuracy` (in percent) is used.
+/// If no accuracy is required, the smallest burst that reaches [DEFAULT_GENERATION_ACCURACY] is used,
+/// or the most accurate one if the default accuracy can not be reached.
 ///
-/// Returns (number of packets, timeout)
-pub fn calculate_send_behaviour(frame_size: u32, traffic_rate: f32, max_burst: u16) -> (u16, u32) {
-    let mut problem = highs::RowProblem::default();
+/// Returns (number of packets, timeout) or an error if the requested rate or accuracy can not be generated.
+/// The error lists the best achievable alternatives.
+pub fn calculate_send_behaviour(frame_size: u32, traffic_rate: f32, max_burst: u16, required_accuracy: Option<f32>) -> Result<(u16, u32), P4TGError> {
+    let key = (frame_size, traffic_rate.to_bits(), max_burst, required_accuracy.map(|a| a.to_bits()));
+
+    if let Some(behaviour) = SEND_BEHAVIOUR_CACHE.lock().unwrap().get(&key) {
+        return Ok(*behaviour);
+    }
+
+    let behaviour = solve_send_behaviour(frame_size, traffic_rate, max_burst, required_accuracy)?;
+
+    let cache = &mut SEND_BEHAVIOUR_CACHE.lock().unwrap();
+
+    if cache.len() >= SEND_BEHAVIOUR_CACHE_SIZE {
+        cache.clear();
+    }
+
+    cache.insert(key, behaviour);
+
+    Ok(behaviour)
+}
+
+fn solve_send_behaviour(frame_size: u32, traffic_rate: f32, max_burst: u16, required_accuracy: Option<f32>) -> Result<(u16, u32), P4TGError> {
+    if frame_size == 0 || !traffic_rate.is_finite() || traffic_rate <= 0f32 {
+        warn!("No solution available. Requested rate {} with frame size {}", traffic_rate, frame_size);
+        return Err(P4TGError::Error { message: format!("No sending behaviour available for rate {} Gbps with frame size {} bytes.", traffic_rate, frame_size) });
+    }
+
+    let bits = frame_size as f64 * 8f64;
+    let rate = traffic_rate as f64;
+    let target = required_accuracy.unwrap_or(DEFAULT_GENERATION_ACCURACY) as f64;
+
+    let accuracy = |(timeout, n_packets): (u64, u64)| rate_accuracy(bits, rate, n_packets, timeout);
+    let candidates = |max_burst: u64| (1..=max_burst).map(|n| (closest_timeout(bits, rate, n), n));
+
+    // candidates are ordered by increasing burst
+    let (timeout, n_packets) = match candidates(max_burst.max(1) as u64).find(|c| accuracy(*c) >= target) {
+        Some(c) => c,
+        None => {
+            let best = most_accurate(candidates(max_burst.max(1) as u64), accuracy);
+
+            if required_accuracy.is_none() {
+                best
+            }
+            else {
+                let required_burst = candidates(u16::MAX as u64).find(|c| accuracy(*c) >= target);
+
+                let alternative = match required_burst {
+                    Some((_, n)) => format!("An accuracy of {:.3}% requires a burst of {} packets.", target, n),
+                    None => format!("An accuracy of {:.3}% can not be reached with any burst.", target)
+                };
+
+                warn!("Required accuracy {}% not reachable. Requested rate {} with frame size {}", target, traffic_rate, frame_size);
+                return Err(P4TGError::Error { message: format!("Required accuracy of {:.3}% not reachable for rate {} Gbps with frame size {} bytes and a maximal burst of {} packets. Best achievable accuracy: {:.3}% with a burst of {} packets. {}",
+                                                               target, traffic_rate, frame_size, max_burst, accuracy(best), best.1, alternative) });
+            }
+        }
+    };
+
+    if timeout > u32::MAX as u64 {
+        warn!("No solution available. Requested rate {} with frame size {}", traffic_rate, frame_size);
+        return Err(P4TGError::Error { message: format!("Rate {} Gbps with frame size {} bytes requires a timeout larger than {} ns.", traffic_rate, frame_size, u32::MAX) });
+    }
+
+    Ok((n_packets as u16, timeout as u32))
+}
+
+/// Accuracy in percent of the rate that is generated with `n_packets` of `bits` per `timeout` ns
+/// compared to the requested `rate` in Gbps.
+fn rate_accuracy(bits: f64, rate: f64, n_packets: u64, timeout: u64) -> f64 {
+    let generated = n_packets as f64 * bits / timeout as f64;
 
-    let accuracy = 0.001;
-    let real_iat = frame_size as f32 * 8f32 / traffic_rate;
-    let d = (real_iat - real_iat.floor()) / real_iat;
+    100f64 * (1f64 - (rate - generated).abs() / rate)
+}
 
-    let max_packets = if max_burst == 1 { 1 } else { (( d / accuracy) as u32) + 1};
+/// Returns the timeout in ns for `n_packets` of `bits` that is closest to the requested `rate` in Gbps.
+/// The generated rate may exceed the requested rate by at most one bit per timeout.
+fn closest_timeout(bits: f64, rate: f64, n_packets: u64) -> u64 {
+    let total_bits = n_packets as f64 * bits;
 
-    // calc + num packets for objective
-    let calculation = problem.add_column(1., 0..100);
-    let num_packets = problem.add_integer_column(1., 1..max_packets);
+    // timeout with a generated rate below or equal to the requested rate
+    let upper = (total_bits / rate).ceil().max(1f64) as u64;
+    // smallest timeout with at most one bit more than requested
+    let lower = ((total_bits - 1f64) / rate).ceil().max(1f64) as u64;
 
-    // not part of objective, therefore factor 0
-    let timeout = problem.add_integer_column(0., 1..u32::MAX); // timeout in 32bit ns
+    if rate_accuracy(bits, rate, n_packets, lower) > rate_accuracy(bits, rate, n_packets, upper) {
+        lower
+    }
+    else {
+        upper
+    }
+}
 
-    // c1: calc - timeout * rate + (num_packets * frame_size * 8) == 0
-    problem.add_row(0..1, [(calculation, 1.), (timeout, (-1f32 * traffic_rate) as f64), (num_packets, (frame_size * 8) as f64)]);
-    problem.add_row(0..u32::MAX, [(calculation, 1.)]);
+/// Returns the most accurate candidate, the one with the smallest burst if there are several.
+fn most_accurate(candidates: impl Iterator<Item=(u64, u64)>, accuracy: impl Fn((u64, u64)) -> f64) -> (u64, u64) {
+    candidates.fold(None, |best: Option<(u64, u64)>, c| match best {
+        Some(b) if accuracy(b) >= accuracy(c) => Some(b),
+        _ => Some(c)
+    }).unwrap()
+}
 
-    let mut solver = problem.optimise(Sense::Minimise);
-    solver.set_option("time_limit", SOLVER_TIME_LIMIT_IN_SECONDS);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let solved = solver.solve();
+    const FRAME_SIZES: [u32; 6] = [64, 128, 512, 1024, 1518, 9000];
+    const TRAFFIC_RATES: [f32; 9] = [0.1, 1.5, 10.0, 25.0, 33.3, 40.0, 99.5, 100.0, 400.0];
+    const MAX_BURSTS: [u16; 4] = [1, 10, 100, 500];
 
-    match solved.status() {
-        HighsModelStatus::Infeasible => {
-            warn!("No solution available. Requested rate {} with frame size {}", traffic_rate, frame_size);
-            (0, 100)
+    fn accuracy(frame_size: u32, traffic_rate: f32, n_packets: u16, timeout: u32) -> f64 {
+        rate_accuracy(frame_size as f64 * 8f64, traffic_rate as f64, n_packets as u64, timeout as u64)
+    }
+
+    /// Generates at most one bit more per timeout than requested
+    fn within_rate(frame_size: u32, traffic_rate: f32, n_packets: u16, timeout: u32) -> bool {
+        n_packets as f64 * frame_size as f64 * 8f64 <= traffic_rate as f64 * timeout as f64 + 1f64
+    }
+
+    /// Best accuracy of all bursts up to `max_burst`, found by scanning the timeouts around the exact inter-arrival time
+    fn best_accuracy(frame_size: u32, traffic_rate: f32, max_burst: u16) -> f64 {
+        (1..=max_burst).flat_map(|n| {
+            let exact = (n as f64 * frame_size as f64 * 8f64 / traffic_rate as f64) as u32;
+
+            (exact.saturating_sub(2).max(1)..=exact + 2)
+                .filter(move |t| within_rate(frame_size, traffic_rate, n, *t))
+                .map(move |t| accuracy(frame_size, traffic_rate, n, t))
+        }).fold(f64::MIN, f64::max)
+    }
+
+    #[test]
+    fn default_accuracy() {
+        for frame_size in FRAME_SIZES {
+            for traffic_rate in TRAFFIC_RATES {
+                for max_burst in MAX_BURSTS {
+                    let (n_packets, timeout) = solve_send_behaviour(frame_size, traffic_rate, max_burst, None).unwrap();
+                    let achieved = accuracy(frame_size, traffic_rate, n_packets, timeout);
+                    let best = best_accuracy(frame_size, traffic_rate, max_burst);
+                    let case = format!("{} bytes @ {} Gbps, burst {}: ({}, {}) with {:.4}%, best {:.4}%", frame_size, traffic_rate, max_burst, n_packets, timeout, achieved, best);
+
+                    assert!(n_packets >= 1 && n_packets <= max_burst, "{}", case);
+                    assert!(within_rate(frame_size, traffic_rate, n_packets, timeout), "{}", case);
+                    assert!(achieved >= best.min(DEFAULT_GENERATION_ACCURACY as f64) - 1e-9, "{}", case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn required_accuracy() {
+        for frame_size in FRAME_SIZES {
+            for traffic_rate in TRAFFIC_RATES {
+                for max_burst in MAX_BURSTS {
+                    for required in [99f32, 99.9, 99.99] {
+                        let best = best_accuracy(frame_size, traffic_rate, max_burst);
+
+                        match solve_send_behaviour(frame_size, traffic_rate, max_burst, Some(required)) {
+                            Ok((n_packets, timeout)) => {
+                                assert!(n_packets >= 1 && n_packets <= max_burst);
+                                assert!(accuracy(frame_size, traffic_rate, n_packets, timeout) >= required as f64);
+                            }
+                            Err(_) => assert!(best < required as f64, "{} bytes @ {} Gbps, burst {}: {}% reachable", frame_size, traffic_rate, max_burst, required)
+                        }
+                    }
+                }
+            }
         }
-        _ => {
-            let solution = solved.get_solution().columns().to_vec();
-            (solution.get(1).unwrap().round() as u16, solution.get(2).unwrap().round() as u32)
+
+        assert!(solve_send_behaviour(64, 100f32, 1, Some(99f32)).is_err());
+        assert!(solve_send_behaviour(64, 0f32, 100, None).is_err());
+    }
+
+    #[test]
+    fn cache_is_bounded() {
+        for i in 0..(2 * SEND_BEHAVIOUR_CACHE_SIZE) {
+            calculate_send_behaviour(64, 1f32 + i as f32 / 100f32, 100, None).unwrap();
         }
+
+        assert!(SEND_BEHAVIOUR_CACHE.lock().unwrap().len() <= SEND_BEHAVIOUR_CACHE_SIZE);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,330 @@
+/* Copyright 2022-present University of Tuebingen, Chair of Communication Networks
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
+ */
+
+use std::collections::{BTreeMap, HashMap};
+
+use rbfrt::SwitchConnection;
+use rbfrt::error::RBFRTError;
+use rbfrt::util::PortManager;
+
+use crate::core::traffic_gen_core::const_definitions::{MAX_STREAM_APP_ID, TWO_PIPE_GENERATION_THRESHOLD};
+use crate::core::traffic_gen_core::helper::{calculate_overhead, speed_to_gbps};
+use crate::core::traffic_gen_core::types::*;
+use crate::error::P4TGError;
+
+/// Assigns the streams to the internal traffic generation ports.
+///
+/// The packet generator runs every application on every pipe.
+/// Packets of a stream are only forwarded on its assigned generation ports and dropped on all other pipes.
+/// Therefore, each pipe generates the sum of `traffic_rate / #assigned ports` over all streams,
+/// while only the assigned streams are forwarded from its generation port.
+///
+/// Streams are split across all generation ports (largest first, IAT precision streams last)
+/// until the generation load of a pipe fits its capacity.
+/// The remaining streams are assigned to the generation port with the lowest forwarded load.
+///
+/// Returns a mapping of app id to generation ports.
+///
+/// # Arguments
+///
+/// * `streams`: List of streams with `traffic_rate` in Gbps
+/// * `ports`: Internal traffic generation ports
+/// * `max_rate`: Maximal rate that can be generated over all ports
+pub fn assign_generation_ports(streams: &[Stream], ports: &[u16], max_rate: f32) -> Result<HashMap<u8, Vec<u16>>, P4TGError> {
+    let n_ports = ports.len() as f32;
+
+    // a single pipe generates accurately up to the threshold.
+    // if all pipes are used, they share the maximal rate
+    let pipe_capacity = TWO_PIPE_GENERATION_THRESHOLD.max(max_rate / n_ports);
+
+    // rate precision streams are split first as bursts from multiple pipes interleave
+    let mut order: Vec<&Stream> = streams.iter().collect();
+    order.sort_by(|a, b| (a.burst == 1).cmp(&(b.burst == 1)).then(b.traffic_rate.total_cmp(&a.traffic_rate)));
+
+    let mut generation_load: f32 = streams.iter().map(|s| s.traffic_rate).sum();
+    let mut split = vec![];
+
+    for s in &order {
+        if generation_load <= TWO_PIPE_GENERATION_THRESHOLD {
+            break;
+        }
+
+        generation_load -= s.traffic_rate - s.traffic_rate / n_ports;
+        split.push(s.app_id);
+    }
+
+    if generation_load > pipe_capacity {
+        return Err(P4TGError::Error { message: format!("Traffic rate of {:.2} Gbps per pipe exceeds the generation capacity of {:.2} Gbps.", generation_load, pipe_capacity) });
+    }
+
+    // forwarded load of each generation port
+    let mut forwarded_load: Vec<f32> = vec![0f32; ports.len()];
+    let mut assignment = HashMap::new();
+
+    for s in order.iter().filter(|s| split.contains(&s.app_id)) {
+        forwarded_load.iter_mut().for_each(|l| *l += s.traffic_rate / n_ports);
+        assignment.insert(s.app_id, ports.to_vec());
+    }
+
+    for s in order.iter().filter(|s| !split.contains(&s.app_id)) {
+        // least loaded port, first port on ties
+        let (index, load) = forwarded_load.iter_mut().enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .ok_or(P4TGError::Error { message: "No traffic generation port available.".to_owned() })?;
+
+        *load += s.traffic_rate;
+        assignment.insert(s.app_id, vec![ports[index]]);
+    }
+
+    Ok(assignment)
+}
+
+/// Resolves the rate overrides of the stream settings.
+///
+/// Stream settings of a stream that share the same rate are replicated by the multicast group of one traffic generation application.
+/// The stream keeps its app id for the settings without override (or for the first override if all settings override the rate).
+/// For each further rate, a copy of the stream with the same stream id, the overridden rate and an unused app id is created.
+/// The app id of each active stream setting is set to the app id that generates it.
+///
+/// # Arguments
+///
+/// * `streams`: List of streams that should be configured
+/// * `stream_settings`: List of stream settings that should be applied
+pub fn resolve_rate_overrides(streams: Vec<Stream>, mut stream_settings: Vec<StreamSetting>) -> Result<(Vec<Stream>, Vec<StreamSetting>), P4TGError> {
+    let used_app_ids: Vec<u8> = streams.iter().map(|s| s.app_id).collect();
+    let mut free_app_ids = (1..=MAX_STREAM_APP_ID).filter(|id| !used_app_ids.contains(id));
+    let mut resolved = vec![];
+
+    for mut stream in streams {
+        // group the settings by rate, the rate of the stream comes first
+        let mut rates: Vec<f32> = vec![];
+
+        for setting in stream_settings.iter().filter(|s| s.active && s.stream_id == stream.stream_id) {
+            let rate = setting.traffic_rate.unwrap_or(stream.traffic_rate);
+
+            if !rates.contains(&rate) {
+                rates.push(rate);
+            }
+        }
+
+        rates.sort_by_key(|rate| *rate != stream.traffic_rate);
+
+        let mut rate_to_app_id: Vec<(f32, u8)> = vec![];
+
+        for (index, rate) in rates.into_iter().enumerate() {
+            let app_id = if index == 0 {
+                stream.app_id
+            }
+            else {
+                free_app_ids.next().ok_or(P4TGError::Error { message: format!("Rate overrides of stream #{} require more than {} traffic generation applications.", stream.stream_id, MAX_STREAM_APP_ID) })?
+            };
+
+            rate_to_app_id.push((rate, app_id));
+        }
+
+        for setting in stream_settings.iter_mut().filter(|s| s.active && s.stream_id == stream.stream_id) {
+            let rate = setting.traffic_rate.unwrap_or(stream.traffic_rate);
+            setting.app_id = rate_to_app_id.iter().find(|(r, _)| *r == rate).map(|(_, app_id)| *app_id);
+        }
+
+        let copies: Vec<Stream> = rate_to_app_id.iter().skip(1).map(|(rate, app_id)| {
+            let mut copy = stream.clone();
+            copy.app_id = *app_id;
+            copy.traffic_rate = *rate;
+            copy
+        }).collect();
+
+        if let Some((rate, _)) = rate_to_app_id.first() {
+            stream.traffic_rate = *rate;
+        }
+
+        resolved.push(stream);
+        resolved.extend(copies);
+    }
+
+    Ok((resolved, stream_settings))
+}
+
+/// Calculates the load of the internal traffic generation ports from the planned streams.
+///
+/// # Arguments
+///
+/// * `streams`: List of streams with calculated sending behaviour
+/// * `ports`: Internal traffic generation ports
+pub fn generation_port_loads(streams: &[Stream], ports: &[u16]) -> Vec<GenerationPortLoad> {
+    let generated_rate: f32 = streams.iter().map(|s| s.traffic_rate / s.n_pipes.unwrap_or(1) as f32).sum();
+
+    ports.iter().map(|port| {
+        let assigned: Vec<&Stream> = streams.iter()
+            .filter(|s| s.generation_ports.as_ref().is_some_and(|p| p.contains(port)))
+            .collect();
+
+        GenerationPortLoad {
+            port: *port,
+            generated_rate,
+            forwarded_rate: assigned.iter().map(|s| s.traffic_rate / s.n_pipes.unwrap_or(1) as f32).sum(),
+            app_ids: assigned.iter().map(|s| s.app_id).collect()
+        }
+    }).filter(|load| !load.app_ids.is_empty()).collect()
+}
+
+/// Calculates the load of the front panel egress ports.
+/// The worst-case burst overlap assumes that the bursts of all streams (and of all pipes of a stream) on a port arrive at once.
+///
+/// # Arguments
+///
+/// * `streams`: List of streams with calculated sending behaviour
+/// * `stream_settings`: List of stream settings that should be applied
+/// * `port_speeds`: Speed in Gbps of the front panel ports
+pub fn egress_port_loads(streams: &[Stream], stream_settings: &[StreamSetting], port_speeds: &HashMap<u32, f32>) -> Vec<EgressPortLoad> {
+    let mut loads: BTreeMap<u32, EgressPortLoad> = BTreeMap::new();
+
+    for setting in stream_settings.iter().filter(|s| s.active) {
+        let Some(s) = streams.iter().find(|s| setting.belongs_to(s)) else {
+            continue;
+        };
+
+        let speed = port_speeds.get(&setting.port).copied();
+
+        let load = loads.entry(setting.port).or_insert(EgressPortLoad {
+            port: setting.port,
+            speed,
+            rate: 0f32,
+            burst_overlap_packets: 0,
+            burst_overlap_bytes: 0,
+            burst_overlap_duration: None
+        });
+
+        // preamble + inter frame gap (IFG) = 20 bytes
+        let frame_size = s.frame_size + calculate_overhead(s) + 20;
+        let burst = s.n_packets.unwrap_or(1) as u32 * s.n_pipes.unwrap_or(1) as u32;
+
+        load.rate += rate_of(s);
+        load.burst_overlap_packets += burst;
+        load.burst_overlap_bytes += burst as u64 * frame_size as u64;
+        load.burst_overlap_duration = speed.map(|speed| (load.burst_overlap_bytes * 8) as f64 / speed as f64);
+    }
+
+    loads.into_values().collect()
+}
+
+/// Calculates the combined generation accuracy of all streams in percent.
+pub fn combined_accuracy(streams: &[Stream]) -> Option<f32> {
+    let requested: f32 = streams.iter().map(|s| s.traffic_rate).sum();
+
+    if streams.is_empty() || requested <= 0f32 {
+        return None;
+    }
+
+    let generated: f32 = streams.iter().map(rate_of).sum();
+
+    Some(100f32 * (1f32 - ((requested - generated).abs() / requested)))
+}
+
+/// Generated rate in Gbps of a stream according to its generation accuracy.
+fn rate_of(s: &Stream) -> f32 {
+    s.traffic_rate * s.generation_accuracy.unwrap_or(100f32) / 100f32
+}
+
+/// Retrieves the speed in Gbps of all configured front panel ports, keyed by dev port.
+pub async fn port_speeds(pm: &PortManager, switch: &SwitchConnection) -> Result<HashMap<u32, f32>, RBFRTError> {
+    let mut speeds = HashMap::new();
+
+    for port in pm.get_ports(switch).await? {
+        let dev_port = pm.dev_port(port.get_port(), port.get_channel())?;
+        speeds.insert(dev_port, speed_to_gbps(port.get_speed()));
+    }
+
+    Ok(speeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::traffic_gen_core::const_definitions::{TG_MAX_RATE, TG_PIPE_PORTS};
+    use crate::core::traffic_gen_core::test_util::{planned, setting, stream};
+
+    #[test]
+    fn streams_below_threshold_are_not_split() {
+        let assignment = assign_generation_ports(&[stream(1, 64, 30f32, 100), stream(2, 64, 20f32, 100)], &TG_PIPE_PORTS, TG_MAX_RATE).unwrap();
+
+        assert_eq!(assignment[&1], vec![68]);
+        assert_eq!(assignment[&2], vec![196]);
+    }
+
+    #[test]
+    fn largest_stream_is_split_first() {
+        let assignment = assign_generation_ports(&[stream(1, 64, 30f32, 100), stream(2, 64, 60f32, 100)], &TG_PIPE_PORTS, TG_MAX_RATE).unwrap();
+
+        assert_eq!(assignment[&2], vec![68, 196]);
+        assert_eq!(assignment[&1], vec![68]);
+    }
+
+    #[test]
+    fn iat_precision_streams_are_not_split() {
+        let assignment = assign_generation_ports(&[stream(1, 64, 50f32, 1), stream(2, 64, 40f32, 100)], &TG_PIPE_PORTS, TG_MAX_RATE).unwrap();
+
+        assert_eq!(assignment[&2], vec![68, 196]);
+        assert_eq!(assignment[&1], vec![68]);
+    }
+
+    #[test]
+    fn generation_capacity_is_exceeded() {
+        assert!(assign_generation_ports(&[stream(1, 64, 120f32, 100), stream(2, 64, 60f32, 100)], &TG_PIPE_PORTS, TG_MAX_RATE).is_err());
+    }
+
+    #[test]
+    fn egress_loads_of_active_settings() {
+        let streams = [planned(1, 64, 10f32, 4, 1, 100f32), planned(2, 1500, 20f32, 2, 2, 50f32)];
+        let settings = [setting(1, 1, true), setting(1, 2, true), setting(2, 1, true), setting(2, 3, false)];
+        let port_speeds = HashMap::from([(1, 100f32), (2, 10f32)]);
+
+        let loads = egress_port_loads(&streams, &settings, &port_speeds);
+
+        assert_eq!(loads.iter().map(|l| l.port).collect::<Vec<u32>>(), vec![1, 2]);
+
+        // 4 * (64 + 20) bytes + 2 * 2 * (1500 + 20) bytes
+        assert_eq!(loads[0].rate, 20f32);
+        assert_eq!(loads[0].burst_overlap_packets, 8);
+        assert_eq!(loads[0].burst_overlap_bytes, 6416);
+        assert_eq!(loads[0].burst_overlap_duration, Some(513.28));
+
+        assert_eq!(loads[1].rate, 10f32);
+        assert_eq!(loads[1].burst_overlap_packets, 4);
+        assert_eq!(loads[1].burst_overlap_bytes, 336);
+        assert_eq!(loads[1].burst_overlap_duration, Some(268.8));
+    }
+
+    #[test]
+    fn egress_loads_of_rate_overrides() {
+        let mut copy = planned(3, 64, 5f32, 1, 1, 100f32);
+        copy.stream_id = 1;
+
+        let mut overridden = setting(1, 2, true);
+        overridden.app_id = Some(3);
+        let mut default = setting(1, 1, true);
+        default.app_id = Some(1);
+
+        let loads = egress_port_loads(&[planned(1, 64, 10f32, 4, 1, 100f32), copy], &[default, overridden], &HashMap::new());
+
+        assert_eq!(loads[0].rate, 10f32);
+        assert_eq!(loads[1].rate, 5f32);
+        assert_eq!(loads[1].burst_overlap_duration, None);
+    }
+}
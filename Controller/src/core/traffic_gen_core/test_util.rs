@@ -0,0 +1,69 @@
+/* Copyright 2022-present University of Tuebingen, Chair of Communication Networks
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
+ */
+
+use std::net::Ipv4Addr;
+
+use crate::core::traffic_gen_core::types::*;
+
+/// Stream without encapsulation whose stream id equals its app id
+pub fn stream(app_id: u8, frame_size: u32, traffic_rate: f32, burst: u16) -> Stream {
+    Stream {
+        stream_id: app_id,
+        app_id,
+        frame_size,
+        encapsulation: Encapsulation::None,
+        number_of_lse: None,
+        traffic_rate,
+        burst,
+        required_accuracy: None,
+        n_packets: None,
+        timeout: None,
+        generation_accuracy: None,
+        iat_jitter: None,
+        n_pipes: None,
+        generation_ports: None,
+        vxlan: false
+    }
+}
+
+/// Stream setting with fixed addresses
+pub fn setting(stream_id: u8, port: u32, active: bool) -> StreamSetting {
+    StreamSetting {
+        port,
+        stream_id,
+        vlan: None,
+        mpls_stack: None,
+        ethernet: Ethernet { eth_src: "00:d0:67:a2:a9:42".to_owned(), eth_dst: "d6:67:75:a1:94:c3".to_owned() },
+        ip: IPv4 { ip_src: Ipv4Addr::new(192, 168, 178, 10), ip_dst: Ipv4Addr::new(192, 168, 178, 11), ip_tos: 0,
+                   ip_src_mask: Ipv4Addr::new(0, 0, 0, 0), ip_dst_mask: Ipv4Addr::new(0, 0, 0, 0) },
+        active,
+        vxlan: None,
+        traffic_rate: None,
+        app_id: None
+    }
+}
+
+/// Stream with the sending behaviour of the planner
+pub fn planned(app_id: u8, frame_size: u32, traffic_rate: f32, n_packets: u16, n_pipes: u8, generation_accuracy: f32) -> Stream {
+    let mut s = stream(app_id, frame_size, traffic_rate, 100);
+    s.n_packets = Some(n_packets);
+    s.n_pipes = Some(n_pipes);
+    s.generation_accuracy = Some(generation_accuracy);
+    s
+}
@@ -127,6 +127,13 @@ pub struct TrafficGenData {
     /// Mapping between TX (send) ports, and RX (receive) ports.
     /// Traffic send on port TX are expected to be received on port RX.
     pub(crate) port_tx_rx_mapping: HashMap<u32, u32>,
+    /// Optional sets of RX ports per TX port, e.g., for LAG or ECMP tests.
+    /// Traffic send on port TX is expected to be received on any port of the set.
+    /// A set replaces the RX port of `port_tx_rx_mapping` for the same TX port.
+    /// Packet loss and out of order packets are accounted across all ports of a set.
+    /// Several TX ports may send to the same RX port (incast) with different streams.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) port_tx_rx_groups: Option<HashMap<u32, Vec<u32>>>,
     /// Optional duration for each traffic generation
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) duration: Option<u64>,
@@ -135,7 +142,88 @@ pub struct TrafficGenData {
     pub(crate) name: Option<String>,
     /// Optional all traffic configurations
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub(crate) all_test: Option<BTreeMap<u32, TrafficGenData>>
+    pub(crate) all_test: Option<BTreeMap<u32, TrafficGenData>>,
+    /// Optional traffic filters for the [GenerationMode::Analyze] mode.
+    /// If set, only frames that match at least one filter are counted in the rate, IAT, and frame type statistics.
+    /// All other frames are still forwarded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) analyze_filters: Option<Vec<AnalyzeFilter>>,
+    /// Optional flow classification rules for the [GenerationMode::Analyze] mode.
+    /// Packets and bytes of frames that match a rule are counted per RX port and rule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) flow_rules: Option<Vec<FlowRule>>
+}
+
+/// Traffic filter for the [GenerationMode::Analyze] mode.
+/// A frame matches the filter if it matches all set fields.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AnalyzeFilter {
+    /// RX port on which the filter is applied. The filter is applied on all RX ports if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u32>,
+    /// VLAN ID of 802.1Q tagged frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vlan_id: Option<u16>,
+    /// Source IPv4 prefix
+    #[schema(example = "192.168.178.0")]
+    #[schema(value_type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_src: Option<Ipv4Addr>,
+    /// Prefix length of the source IPv4 prefix. Defaults to 32.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_src_prefix: Option<u8>,
+    /// Destination IPv4 prefix
+    #[schema(example = "10.0.0.0")]
+    #[schema(value_type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_dst: Option<Ipv4Addr>,
+    /// Prefix length of the destination IPv4 prefix. Defaults to 32.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_dst_prefix: Option<u8>,
+    /// DSCP value of IPv4 frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dscp: Option<u8>
+}
+
+/// Flow classification rule for the [GenerationMode::Analyze] mode.
+/// A frame belongs to the flow if it matches all set fields.
+/// If a frame matches multiple rules, it is counted for the first matching rule.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FlowRule {
+    /// Unique name of the flow. Used as key in the flow statistics.
+    #[schema(example = "dns")]
+    pub name: String,
+    /// RX port on which the rule is applied. The rule is applied on all RX ports if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u32>,
+    /// VLAN ID of 802.1Q tagged frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vlan_id: Option<u16>,
+    /// Source IPv4 prefix
+    #[schema(example = "192.168.178.0")]
+    #[schema(value_type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_src: Option<Ipv4Addr>,
+    /// Prefix length of the source IPv4 prefix. Defaults to 32.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_src_prefix: Option<u8>,
+    /// Destination IPv4 prefix
+    #[schema(example = "10.0.0.0")]
+    #[schema(value_type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_dst: Option<Ipv4Addr>,
+    /// Prefix length of the destination IPv4 prefix. Defaults to 32.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_dst_prefix: Option<u8>,
+    /// IP protocol number, e.g., 6 for TCP and 17 for UDP
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<u8>,
+    /// TCP/UDP source port. Requires `protocol` to be TCP or UDP.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub src_port: Option<u16>,
+    /// TCP/UDP destination port. Requires `protocol` to be TCP or UDP.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dst_port: Option<u16>
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -198,7 +286,23 @@ pub struct StreamSetting {
     pub active: bool,
     /// VxLAN tunnel settings
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub vxlan: Option<VxLAN>
+    pub vxlan: Option<VxLAN>,
+    /// Overrides the traffic rate of the stream on this port. Uses the same unit as the traffic rate of the stream.
+    /// Stream settings with a different rate are generated by an additional traffic generation application.
+    #[schema(example = 10)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub traffic_rate: Option<f32>,
+    /// Application id that generates this stream setting. Set internally when rate overrides are resolved.
+    #[serde(skip)]
+    pub(crate) app_id: Option<u8>
+}
+
+impl StreamSetting {
+    /// Checks if this stream setting is generated by the stream.
+    /// If rate overrides are resolved, the app id has to match as well.
+    pub(crate) fn belongs_to(&self, stream: &Stream) -> bool {
+        self.stream_id == stream.stream_id && (self.app_id.is_none() || self.app_id == Some(stream.app_id))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -222,9 +326,14 @@ pub struct Stream {
     /// Traffic rate in Gbps that should be generated.
     #[schema(example = 100)]
     pub(crate) traffic_rate: f32,
-    /// Maximal allowed burst (= packets). Burst = 1 is used for IAT precision mode, Burst = 100 for Rate precision.
+    /// Maximal allowed burst (= packets that are generated at once). Burst = 1 is used for IAT precision mode, Burst = 100 for Rate precision.
     #[schema(example = 100)]
     pub(crate) burst: u16,
+    /// Required generation accuracy in percent. The smallest burst up to `burst` that reaches this accuracy is used.
+    /// The traffic generation is rejected if the accuracy can not be reached.
+    #[schema(example = 99.99)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) required_accuracy: Option<f32>,
     /// These values are set by P4TG when the stream is generated to indicate the applied configuration.
     #[schema(example = 11)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -238,14 +347,111 @@ pub struct Stream {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) generation_accuracy: Option<f32>,
     /// These values are set by P4TG when the stream is generated to indicate the applied configuration.
+    /// Maximal deviation in ns of the inter-arrival times from their mean that is introduced by bursting (per pipe).
+    #[schema(example = 7.04)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) iat_jitter: Option<f32>,
+    /// These values are set by P4TG when the stream is generated to indicate the applied configuration.
     #[schema(example = 2)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) n_pipes: Option<u8>,
+    /// These values are set by P4TG when the stream is generated to indicate the applied configuration.
+    /// Internal traffic generation ports on which the stream is forwarded.
+    #[schema(example = json!([68, 196]))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) generation_ports: Option<Vec<u16>>,
     /// Flag that indicates if traffic should be encapsulation in VxLAN
     #[schema(example = false)]
     pub(crate) vxlan: bool
 }
 
+/// Describes a packet in the internal packet buffer of the traffic generator
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct PacketBufferEntry {
+    /// Application id of the traffic generator that generates the packet
+    pub(crate) app_id: u8,
+    /// Offset (bytes) in the internal packet buffer
+    pub(crate) buffer_offset: u32,
+    /// Size of the packet in bytes (without CRC)
+    pub(crate) size: u32,
+    /// Number of packets that are sent per `timer` ns
+    pub(crate) n_packets: u16,
+    /// Timeout for the packet generation in ns
+    pub(crate) timer: u32
+}
+
+/// Describes a table entry that rewrites the headers of a stream on an egress port
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct HeaderRewriteEntry {
+    /// Name of the P4 table
+    pub(crate) table: String,
+    /// Action of the table entry
+    pub(crate) action: String,
+    /// Front panel port of the stream setting
+    pub(crate) port: u32,
+    /// TX recirculation port on which the rewrite takes place
+    pub(crate) egress_port: u32,
+    /// Application id of the rewritten stream
+    pub(crate) app_id: u8
+}
+
+/// Describes the load of an internal traffic generation port
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct GenerationPortLoad {
+    /// Internal traffic generation port
+    pub(crate) port: u16,
+    /// Rate in Gbps that is generated on the pipe of this port, including packets that are dropped
+    /// because their stream is assigned to another generation port
+    pub(crate) generated_rate: f32,
+    /// Rate in Gbps of the streams that are forwarded from this port
+    pub(crate) forwarded_rate: f32,
+    /// App ids of the streams that are forwarded from this port
+    pub(crate) app_ids: Vec<u8>
+}
+
+/// Describes the load of a front panel egress port
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct EgressPortLoad {
+    /// Front panel port (dev port)
+    pub(crate) port: u32,
+    /// Speed of the port in Gbps, if the port is known to the port manager
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) speed: Option<f32>,
+    /// Sum of the generated rates in Gbps of all streams on this port
+    pub(crate) rate: f32,
+    /// Number of packets that arrive at once if the bursts of all streams on this port overlap
+    pub(crate) burst_overlap_packets: u32,
+    /// Number of bytes (including preamble and IFG) that arrive at once if the bursts of all streams on this port overlap
+    pub(crate) burst_overlap_bytes: u64,
+    /// Time in ns the port needs to transmit the overlapping bursts with its speed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) burst_overlap_duration: Option<f64>
+}
+
+/// Represents the body of the POST endpoint of /trafficgen/plan.
+/// Contains everything that would be configured for a traffic generation without touching the switch.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct TrafficGenPlan {
+    /// Streams including the calculated sending behaviour
+    /// (`n_packets`, `timeout`, `generation_accuracy` and `n_pipes`).
+    pub(crate) streams: Vec<Stream>,
+    /// Combined generation accuracy of all streams, i.e., generated rate in relation to the requested rate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) generation_accuracy: Option<f32>,
+    /// Load of the internal traffic generation ports.
+    pub(crate) generation_ports: Vec<GenerationPortLoad>,
+    /// Load of the front panel egress ports.
+    pub(crate) egress_ports: Vec<EgressPortLoad>,
+    /// Layout of the internal packet buffer of the traffic generator.
+    pub(crate) packet_buffer: Vec<PacketBufferEntry>,
+    /// Multicast groups that replicate a stream. Maps the multicast group id to the TX recirculation ports.
+    pub(crate) multicast_groups: BTreeMap<u16, Vec<u32>>,
+    /// Header rewrite table entries that would be installed in the egress.
+    pub(crate) header_rewrites: Vec<HeaderRewriteEntry>,
+    /// Validation errors of the configuration. The remaining fields are empty if errors exist.
+    pub(crate) errors: Vec<String>
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct EmptyResponse {
     #[schema(example = "Not running.")]
@@ -262,6 +468,9 @@ pub struct Reset {
 pub struct TestResult {
     pub(crate) throughput: Option<BTreeMap<u32, f32>>,
     pub(crate) latency: Option<BTreeMap<u32, f64>>,
+    /// One-way latency per frame size and stream (app_id)
+    #[serde(default)]
+    pub(crate) stream_latency: Option<BTreeMap<u32, BTreeMap<u32, f64>>>,
     pub(crate) frame_loss_rate: Option<BTreeMap<u32, BTreeMap<u32, f64>>>, 
     pub(crate) reset: Option<BTreeMap<u32, f64>>,
     pub(crate) running: bool,
@@ -16,3 +16,15 @@ impl From<P4TGError> for RBFRTError {
     }
 }
 
+
+/// Error of the traffic generation setup.
+/// Distinguishes invalid traffic generation requests from errors of the switch configuration.
+#[derive(Error, Debug)]
+pub enum TrafficGenError {
+    /// The requested traffic generation can not be generated, e.g., the rate or accuracy is not reachable
+    #[error(transparent)]
+    Invalid(#[from] P4TGError),
+    /// The switch could not be configured
+    #[error("{0:?}")]
+    Switch(#[from] RBFRTError)
+}
@@ -17,12 +17,12 @@
 /*
  * Steffen Lindner (steffen.lindner@uni-tuebingen.de)
  */
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::fs::File;
 use std::str::FromStr;
 use std::sync::Arc;
-use api::statistics::Statistics;
+use std::time::Duration;
+use api::statistics::{LossEvents, Statistics};
 use rbfrt::SwitchConnection;
 use log::{info, warn};
 use macaddr::MacAddr;
@@ -30,7 +30,7 @@ use rbfrt::error::RBFRTError;
 use rbfrt::util::port_manager::{AutoNegotiation, FEC, Loopback, Port, Speed};
 use rbfrt::util::port_manager::FEC::BF_FEC_TYP_REED_SOLOMON;
 use rbfrt::util::PortManager;
-use tokio::sync::{Mutex, watch};
+use tokio::sync::{Mutex, RwLock, watch};
 use core::statistics::TimeStatistic;
 use core::traffic_gen_core::types::TrafficGenData;
 use crate::core::traffic_gen_core::types::*;
@@ -41,11 +41,14 @@ mod api;
 mod error;
 
 use core::FrameSizeMonitor;
-use crate::core::{Arp, Config, FrameTypeMonitor, RateMonitor, TrafficGen};
-use crate::core::traffic_gen_core::const_definitions::{PORT_CFG_TF2};
+use crate::core::config::Connection;
+use crate::api::ports::PortConfiguration;
+use crate::core::{Arp, Config, FlowMonitor, FrameTypeMonitor, RateMonitor, TrafficGen};
+use crate::core::traffic_gen_core::const_definitions::{MAX_FRONT_PANEL_PORT, PORT_CFG_TF2, RECONNECT_INTERVAL};
 use crate::core::traffic_gen_core::event::TrafficGenEvent;
+use crate::error::P4TGError;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct PortMapping {
     pub tx_recirculation: u32,
     pub rx_recirculation: u32,
@@ -62,6 +65,7 @@ pub struct Experiment {
 pub struct MultiTest {
     pub(crate) collected_statistics: Mutex<Vec<Statistics>>,
     pub(crate) collected_time_statistics: Mutex<Vec<TimeStatistic>>,
+    pub(crate) collected_loss_events: Mutex<Vec<LossEvents>>,
     pub(crate) multiple_traffic_generators: Mutex<Vec<TrafficGenData>>,
     pub(crate) abort_sender: Mutex<Option<watch::Sender<()>>>,
     pub(crate) rfc_results: Mutex<TestResult>, 
@@ -72,15 +76,23 @@ pub struct MultiTest {
 pub struct AppState {
     pub(crate) frame_size_monitor: Mutex<FrameSizeMonitor>,
     pub(crate) frame_type_monitor: Mutex<FrameTypeMonitor>,
+    pub(crate) flow_monitor: Mutex<FlowMonitor>,
     pub(crate) traffic_generator: Mutex<TrafficGen>,
     pub(crate) port_mapping: HashMap<u32, PortMapping>,
     pub(crate) rate_monitor: Mutex<RateMonitor>,
-    pub(crate) switch: SwitchConnection,
+    pub(crate) switch: RwLock<SwitchConnection>,
+    /// Indicates if the connection to the switch is established.
+    /// The connection is lost if bf_switchd restarts.
+    pub(crate) connected: Mutex<bool>,
+    pub(crate) p4_name: String,
     pub(crate) pm: PortManager,
     pub(crate) experiment: Mutex<Experiment>,
     pub(crate) sample_mode: bool,
     pub(crate) config: Mutex<Config>,
     pub(crate) arp_handler: Arp,
+    /// Port configurations that were changed via the REST API per dev port.
+    /// They are replayed after a reconnect to the switch.
+    pub(crate) port_configurations: Mutex<BTreeMap<u32, PortConfiguration>>,
     pub(crate) multi_test_state: MultiTest, 
     pub(crate) tofino2: bool,
     pub(crate) loopback_mode: bool
@@ -96,24 +108,43 @@ async fn configure_ports(switch: &mut SwitchConnection, pm: &PortManager, config
     let mut tg_ports = vec![];
 
     // TG_PORTS
+    // each breakout channel is an individual traffic generation port
     for tg in &config.tg_ports {
-        let mut pm_req = Port::new(tg.port , 0)
-            .speed(if is_tofino2 {Speed::BF_SPEED_400G} else {Speed::BF_SPEED_100G})
-            .fec(if is_tofino2 {BF_FEC_TYP_REED_SOLOMON} else {FEC::BF_FEC_TYP_NONE})
-            .auto_negotiation(AutoNegotiation::PM_AN_DEFAULT);
+        tg.validate_breakout(is_tofino2).map_err(|message| P4TGError::Error { message })?;
 
-        if loopback_mode { // loopback mode is used for testing if no cables are available
-            pm_req = pm_req.loopback(Loopback::BF_LPBK_MAC_NEAR);
-        }
+        for channel in tg.channels() {
+            if pm.dev_port(tg.port, channel).is_err() {
+                return Err(P4TGError::Error { message: format!("Traffic generation port {}/{} is not available on the device.", tg.port, channel) }.into());
+            }
 
-        // we validated the mac address before
-        tg_ports.push((tg.port, MacAddr::from_str(&tg.mac).unwrap()));
+            let mut pm_req = Port::new(tg.port, channel)
+                .speed(tg.speed.clone().unwrap_or(if is_tofino2 {Speed::BF_SPEED_400G} else {Speed::BF_SPEED_100G}))
+                .fec(tg.fec.clone().unwrap_or(if is_tofino2 {BF_FEC_TYP_REED_SOLOMON} else {FEC::BF_FEC_TYP_NONE}))
+                .auto_negotiation(tg.auto_neg.clone().unwrap_or(AutoNegotiation::PM_AN_DEFAULT));
 
-        port_requests.push(pm_req);
+            if loopback_mode { // loopback mode is used for testing if no cables are available
+                pm_req = pm_req.loopback(Loopback::BF_LPBK_MAC_NEAR);
+            }
+
+            // we validated the mac address before
+            tg_ports.push((tg.port, channel, MacAddr::from_str(&tg.mac).unwrap()));
+
+            port_requests.push(pm_req);
+        }
+    }
+
+    // two recirculation ports are required per traffic generation port
+    if recirculation_ports.len() < tg_ports.len() * 2 {
+        return Err(P4TGError::Error { message: format!("{} traffic generation ports require {} recirculation ports, but only {} are available.",
+                                                       tg_ports.len(), tg_ports.len() * 2, recirculation_ports.len()) }.into());
     }
 
     // Recirculation ports
     for port in recirculation_ports {
+        if pm.dev_port(*port, 0).is_err() {
+            return Err(P4TGError::Error { message: format!("Recirculation port {} is not available on the device.", port) }.into());
+        }
+
         let pm_req = Port::new(*port , 0)
             .speed(if is_tofino2 {Speed::BF_SPEED_400G} else {Speed::BF_SPEED_100G})
             .fec(if is_tofino2 {BF_FEC_TYP_REED_SOLOMON} else {FEC::BF_FEC_TYP_NONE})
@@ -129,8 +160,8 @@ async fn configure_ports(switch: &mut SwitchConnection, pm: &PortManager, config
 
     port_mapping.clear();
 
-    for (offset, (index, (port, mac))) in tg_ports.iter().enumerate().enumerate() {
-        let dev_port = pm.dev_port(*port, 0)?;
+    for (offset, (index, (port, channel, mac))) in tg_ports.iter().enumerate().enumerate() {
+        let dev_port = pm.dev_port(*port, *channel)?;
         let tx_port = pm.dev_port(*recirculation_ports.get(index+offset).unwrap(), 0)?;
         let rx_port = pm.dev_port(*recirculation_ports.get(index+offset+1).unwrap(), 0)?;
 
@@ -145,10 +176,11 @@ async fn configure_ports(switch: &mut SwitchConnection, pm: &PortManager, config
         used_recirculation_ports.push(port.rx_recirculation);
     }
 
+    used_recirculation_ports.sort();
     used_recirculation_ports.dedup();
 
     if used_recirculation_ports.len() != tg_ports.len() * 2 {
-        panic!("Recirculation ports not unique.")
+        return Err(P4TGError::Error { message: "Recirculation ports not unique.".to_owned() }.into());
     }
 
     Ok(())
@@ -159,15 +191,96 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
     let sample_mode = sample_mode == 1;
     let p4_name = env::var("P4_NAME").unwrap_or("traffic_gen".to_owned());
     let loopback_mode = env::var("LOOPBACK").unwrap_or("0".to_owned()).parse().unwrap_or(false);
+    let reset_ports = env::var("RESET_PORTS").unwrap_or("0".to_owned()).parse().unwrap_or(0) == 1;
 
     info!("Start controller...");
 
-    let mut switch = SwitchConnection::new("localhost", 50052)
-        .device_id(0)
-        .client_id(1)
-        .p4_name(&p4_name)
-        .connect()
-        .await?;
+    if loopback_mode {
+        info!("Loopback mode activated.");
+    }
+
+    // one config per device, either from config file or default
+    let configs = Config::load("/app/config.json");
+
+    let mut states = vec![];
+
+    for config in configs {
+        states.push(init_device(config, &p4_name, sample_mode, loopback_mode).await?);
+    }
+
+    let shutdown_states = states.clone();
+
+    // start rest API
+    // the server shuts down after the devices are cleaned up on SIGTERM / SIGINT
+    api::server::start_api_server(states, async move {
+        shutdown_signal().await;
+
+        info!("Shutting down controller...");
+
+        for state in shutdown_states {
+            if let Err(err) = shutdown(&state, reset_ports).await {
+                warn!("Error while shutting down device {}: {}", state.config.lock().await.name(), err);
+            }
+        }
+    }).await;
+
+    Ok(())
+}
+
+/// Waits for SIGINT (Ctrl+C) or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to install SIGINT handler.");
+    };
+
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler.")
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {}
+    }
+}
+
+/// Cleans up the switch state of a device before the controller shuts down.
+/// Aborts a running profile, stops the traffic generation and removes the multicast groups.
+/// If `reset_ports` is set, the ports of the device are removed.
+async fn shutdown(state: &AppState, reset_ports: bool) -> Result<(), RBFRTError> {
+    if let Some(sender) = state.multi_test_state.abort_sender.lock().await.as_ref() {
+        let _ = sender.send(());
+    }
+
+    let switch = &*state.switch.read().await;
+
+    // the ports are reset even if the traffic generation could not be cleaned up
+    if let Err(err) = state.traffic_generator.lock().await.shutdown(switch).await {
+        warn!("Error while stopping the traffic generation of device {}: {:?}", state.config.lock().await.name(), err);
+    }
+
+    state.experiment.lock().await.running = false;
+
+    if reset_ports {
+        switch.clear_table("$PORT").await?;
+    }
+
+    info!("Device {} cleaned up.", state.config.lock().await.name());
+
+    Ok(())
+}
+
+/// Connects to a device, configures its ports and tables, and starts its monitoring tasks.
+///
+/// Returns the [AppState] of the device.
+async fn init_device(config: Config, p4_name: &str, sample_mode: bool, loopback_mode: bool) -> Result<Arc<AppState>, Box<dyn std::error::Error>> {
+    let connection = config.connection();
+
+    info!("Connect to device {} ({}:{}, device id {}).", config.name(), connection.host, connection.port, connection.device_id);
+
+    let mut switch = connect(&connection, p4_name).await?;
 
     // check if its tofino 1 or tofino 2
     // this could be done more intelligent
@@ -181,38 +294,7 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
         info!("ASIC: Tofino1");
     }
 
-    if loopback_mode {
-        info!("Loopback mode activated.");
-    }
-
-    // Front panel ports that can be used for traffic generation.
-    // At default, the first 10 ports are used for traffic generation.
-    let all_ports: Vec<u32> = (1..33).collect(); // we dont have a 64-port Tofino for testing purposes
-                                                 // limit to 32 ports
-
-    // TG ports either from config or default
-    let config = match File::open("/app/config.json") {
-        Ok(file) => {
-            let mut config: Config = serde_json::from_reader(file).unwrap_or_else(|_| {
-                warn!("Config file not valid. Using default config.");
-                Config::default()
-            });
-
-            if !config.validate() {
-                warn!("Config not valid. At most 10 ports can be used, and no front panel port > 32. \
-                       Mac addresses need to be correct. \
-                       Using default config.");
-                config = Config::default();
-            }
-
-            config
-
-        }
-        Err(_) => {
-            warn!("No config file (/app/config.json) for controller found. Using default config.");
-            Config::default()
-        }
-    };
+    let pm = PortManager::new(&switch).await;
 
     if config.tg_ports.is_empty() {
         panic!("No traffic generation ports should be configured.");
@@ -220,30 +302,33 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 
     // Front panel ports that are used for recirculation purposes
     // Recirculations are needed for measurement purposes
-    let recirculation_ports: Vec<u32> = all_ports.into_iter().filter(|p| !config.contains(*p)).collect();
+    let recirculation_ports = config.recirculation_ports(&front_panel_ports(&pm));
 
     let mut port_mapping: HashMap<u32, PortMapping> = HashMap::new();
 
-    let pm = PortManager::new(&switch).await;
-
     configure_ports(&mut switch, &pm, &config, &recirculation_ports, &mut port_mapping, is_tofino2, loopback_mode).await?;
 
     // configures frame size count tables
-    let frame_size_monitor = FrameSizeMonitor::new(port_mapping.clone());
+    let frame_size_monitor = FrameSizeMonitor::new(port_mapping.clone(), config.frame_size_bins.clone());
 
     // configures frame type count tables (multicast, broadcast, vlan, ipv4, ...)
     let frame_type_monitor = FrameTypeMonitor::new(port_mapping.clone());
 
+    // configures the flow counters of the analyze mode
+    let flow_monitor = FlowMonitor::new(port_mapping.clone());
+
     // configures rate monitoring and monitoring packets related tables
     let mut rate_monitor = RateMonitor::new(port_mapping.clone());
     rate_monitor.init_rtt_meter(&switch).await?;
     rate_monitor.init_iat_meter(&switch, sample_mode).await?;
+    rate_monitor.init_reorder_buckets(&switch).await?;
     rate_monitor.on_reset(&switch).await?;
 
     let mut traffic_generator = TrafficGen::new(is_tofino2);
     traffic_generator.stop(&switch).await?;
 
     let index_mapping = traffic_generator.init_monitoring_packet(&switch, &port_mapping).await?;
+    rate_monitor.set_index_mapping(&index_mapping);
 
     let arp_handler = Arp::new();
     arp_handler.init(&switch, &port_mapping).await?;
@@ -251,18 +336,23 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
     let state = Arc::new(AppState {
         frame_size_monitor: Mutex::new(frame_size_monitor),
         frame_type_monitor: Mutex::new(frame_type_monitor),
+        flow_monitor: Mutex::new(flow_monitor),
         traffic_generator: Mutex::new(traffic_generator),
         port_mapping,
         rate_monitor: Mutex::new(rate_monitor),
-        switch,
+        switch: RwLock::new(switch),
+        connected: Mutex::new(true),
+        p4_name: p4_name.to_owned(),
         pm,
         sample_mode,
         experiment: Mutex::new(Experiment { start: std::time::SystemTime::now(), running: false }),
         config: Mutex::new(config),
         arp_handler,
+        port_configurations: Mutex::new(BTreeMap::new()),
         multi_test_state: MultiTest {
         collected_statistics: Mutex::new(Vec::new()),
         collected_time_statistics: Mutex::new(Vec::new()),
+        collected_loss_events: Mutex::new(Vec::new()),
         multiple_traffic_generators: Mutex::new(Vec::new()),
         rfc_results: Mutex::new(TestResult::default()),
         abort_sender: Mutex::new(None),
@@ -271,8 +361,9 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
         loopback_mode
     });
 
-    state.frame_size_monitor.lock().await.configure(&state.switch).await?;
-    state.frame_type_monitor.lock().await.configure(&state.switch).await?;
+    state.frame_size_monitor.lock().await.configure(&*state.switch.read().await).await?;
+    state.frame_type_monitor.lock().await.configure(&*state.switch.read().await).await?;
+    state.flow_monitor.lock().await.configure(&*state.switch.read().await).await?;
 
     let monitoring_state = Arc::clone(&state);
 
@@ -285,6 +376,15 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 
     let monitoring_state = Arc::clone(&state);
 
+    // start duplicate and reordering monitoring
+    tokio::spawn(async move {
+        let local_state = monitoring_state;
+
+        RateMonitor::monitor_reordering(local_state).await;
+    });
+
+    let monitoring_state = Arc::clone(&state);
+
     // start frame type monitoring
     tokio::spawn(async move {
         let local_state = monitoring_state;
@@ -303,17 +403,133 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 
     let monitoring_state = Arc::clone(&state);
 
-    // start digest monitoring
+    // start flow monitoring
     tokio::spawn(async move {
         let local_state = monitoring_state;
 
-        RateMonitor::monitor_digests(local_state, &index_mapping, sample_mode).await;
+        FlowMonitor::monitor_statistics(local_state).await;
     });
 
-    // start rest API
-    api::server::start_api_server(Arc::clone(&state)).await;
+    let monitoring_state = Arc::clone(&state);
 
-    Ok(())
+    // start digest monitoring and reconnect if the connection to the switch is lost
+    tokio::spawn(async move {
+        let local_state = monitoring_state;
+
+        supervise_connection(local_state, index_mapping).await;
+    });
+
+    Ok(state)
+}
+
+/// Connects to the BF Runtime server of a device.
+async fn connect(connection: &Connection, p4_name: &str) -> Result<SwitchConnection, RBFRTError> {
+    SwitchConnection::new(&connection.host, connection.port)
+        .device_id(connection.device_id)
+        .client_id(connection.client_id)
+        .p4_name(p4_name)
+        .connect()
+        .await
+}
+
+/// Front panel ports of the device that can be used for traffic generation and recirculation.
+fn front_panel_ports(pm: &PortManager) -> Vec<u32> {
+    (1..=MAX_FRONT_PANEL_PORT).filter(|p| pm.dev_port(*p, 0).is_ok()).collect()
+}
+
+/// Monitors the digests of the switch and supervises the connection.
+///
+/// The digest queue is closed if the connection to the switch is lost, e.g., if bf_switchd restarts.
+/// A hung switch is detected if no digests arrive for [DIGEST_TIMEOUT](crate::core::traffic_gen_core::const_definitions::DIGEST_TIMEOUT) seconds and the switch does not respond.
+/// Afterwards, running traffic generations are stopped and the supervisor reconnects
+/// every [RECONNECT_INTERVAL] seconds until the configuration of the device is replayed.
+async fn supervise_connection(state: Arc<AppState>, mut index_mapping: HashMap<u32, MonitoringMapping>) {
+    loop {
+        RateMonitor::monitor_digests(Arc::clone(&state), &index_mapping, state.sample_mode).await;
+
+        warn!("Connection to switch lost.");
+
+        *state.connected.lock().await = false;
+
+        // the data plane state of a running traffic generation is lost
+        state.experiment.lock().await.running = false;
+        state.traffic_generator.lock().await.running = false;
+
+        if let Some(sender) = state.multi_test_state.abort_sender.lock().await.as_ref() {
+            let _ = sender.send(());
+        }
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(RECONNECT_INTERVAL)).await;
+
+            match reconnect(&state).await {
+                Ok(mapping) => {
+                    index_mapping = mapping;
+                    break;
+                }
+                Err(err) => {
+                    warn!("Reconnect to switch failed: {}", err);
+                }
+            }
+        }
+
+        *state.connected.lock().await = true;
+
+        info!("Reconnected to switch.");
+    }
+}
+
+/// Reconnects to the switch and replays the configuration of the device:
+/// ports (including the changes via the REST API), rate monitoring, monitoring packets, ARP replies, and frame size / type monitoring.
+/// Fails if the port mapping of the device changed, e.g., due to a changed config.
+///
+/// Returns the mapping of monitoring index to (port, app id).
+async fn reconnect(state: &AppState) -> Result<HashMap<u32, MonitoringMapping>, Box<dyn std::error::Error>> {
+    let config = state.config.lock().await.clone();
+
+    let mut switch = connect(&config.connection(), &state.p4_name).await?;
+
+    // the port mapping is used by all monitors and can not change without a restart
+    let recirculation_ports = config.recirculation_ports(&front_panel_ports(&state.pm));
+    let mut port_mapping: HashMap<u32, PortMapping> = HashMap::new();
+
+    configure_ports(&mut switch, &state.pm, &config, &recirculation_ports, &mut port_mapping, state.tofino2, state.loopback_mode).await?;
+
+    if port_mapping != state.port_mapping {
+        return Err("Port mapping of the device changed. Restart the controller to apply the new port mapping.".into());
+    }
+
+    // replay the port configurations of the REST API
+    for port_configuration in state.port_configurations.lock().await.values() {
+        state.pm.update_port(&switch, &port_configuration.request(&state.pm, state.loopback_mode)?).await?;
+    }
+
+    {
+        let rate_monitor = &mut state.rate_monitor.lock().await;
+        rate_monitor.init_rtt_meter(&switch).await?;
+        rate_monitor.init_iat_meter(&switch, state.sample_mode).await?;
+        rate_monitor.init_reorder_buckets(&switch).await?;
+        rate_monitor.on_reset(&switch).await?;
+    }
+
+    let index_mapping = {
+        let traffic_generator = &mut state.traffic_generator.lock().await;
+        traffic_generator.stop(&switch).await?;
+        traffic_generator.init_monitoring_packet(&switch, &state.port_mapping).await?
+    };
+
+    state.rate_monitor.lock().await.set_index_mapping(&index_mapping);
+
+    state.arp_handler.init(&switch, &state.port_mapping).await?;
+    state.arp_handler.restore(&switch, &state.port_mapping).await?;
+
+    state.frame_size_monitor.lock().await.configure(&switch).await?;
+    state.frame_type_monitor.lock().await.configure(&switch).await?;
+    state.flow_monitor.lock().await.configure(&switch).await?;
+
+    *state.switch.write().await = switch;
+
+    Ok(index_mapping)
 }
 
 